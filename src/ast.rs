@@ -1,13 +1,28 @@
 use std::{
+    any::Any,
+    cell::RefCell,
     fmt::{self, Debug, Formatter},
     mem,
     rc::Rc,
 };
 
-use crate::{errs::FangErr, scope::Scope, FILE_NAME};
+use crate::{
+    bytecode::{arithmetic, Op},
+    errs::FangErr,
+    scope::Scope,
+    FILE_NAME,
+};
+
+/// The type of a builtin's last parameter when it takes any number of values of any type,
+/// which it receives as a list.
+pub const VARIADIC: &str = "...";
+/// The type of objects holding a sequence of values, in fields named `0`, `1`, ...
+pub const LIST: &str = "list";
+
+type BuiltinFnPtr = Rc<dyn Fn(&Scope) -> Result<Option<Node>, FangErr>>;
 
 #[derive(Clone)]
-pub struct BuiltinFnBody(pub Rc<dyn Fn(&Scope) -> Option<Node>>);
+pub struct BuiltinFnBody(pub BuiltinFnPtr);
 impl Debug for BuiltinFnBody {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "<Builtin Function>")
@@ -20,6 +35,21 @@ impl PartialEq for BuiltinFnBody {
     }
 }
 
+/// A Rust value held by a script, shared by every copy of the handle.
+#[derive(Clone)]
+pub struct NativeHandle(pub Rc<RefCell<dyn Any>>);
+impl Debug for NativeHandle {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<Native>")
+    }
+}
+
+impl PartialEq for NativeHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Spans {
     line: String,
@@ -37,22 +67,59 @@ impl Spans {
     }
 
     pub fn snippet(&self) -> String {
-        vec![
-            format!(
-                "At {}:{}:{}",
-                FILE_NAME.lock().unwrap(),
-                self.line_span.0,
-                self.col_span.0
-            ),
-            format!(""),
-            format!("{}", self.line),
-            format!(
-                "{}{}",
-                " ".repeat(self.col_span.0),
-                "^".repeat(self.col_span.1 - self.col_span.0)
-            ),
-        ]
-        .join("\n")
+        self.render(None)
+    }
+
+    /// Renders the snippet with `label` written after the carets, for secondary locations.
+    pub fn label(&self, label: &str) -> String {
+        self.render(Some(label))
+    }
+
+    pub fn location(&self) -> String {
+        format!(
+            "At {}:{}:{}",
+            FILE_NAME.lock().unwrap(),
+            self.line_span.0,
+            self.col_span.0
+        )
+    }
+
+    fn render(&self, label: Option<&str>) -> String {
+        let location = self.location();
+
+        if self.is_empty() {
+            return location;
+        }
+
+        let width = self.line_span.1.to_string().len();
+        let gutter = " ".repeat(width);
+        let lines = self.line.lines().collect::<Vec<&str>>();
+        let last = lines.len().saturating_sub(1);
+
+        let mut out = vec![location, format!("{gutter} |")];
+        for (i, src) in lines.iter().enumerate() {
+            let len = src.chars().count();
+            let indent = src.chars().take_while(|c| c.is_whitespace()).count();
+            let (from, to) = match (i == 0, i == last) {
+                (true, true) => (self.col_span.0 - 1, self.col_span.1 - 1),
+                (true, false) => (self.col_span.0 - 1, len),
+                (false, true) => (indent, self.col_span.1 - 1),
+                (false, false) => (indent, len),
+            };
+
+            out.push(format!("{:>width$} | {}", self.line_span.0 + i, src));
+            out.push(format!(
+                "{gutter} | {}{}{}",
+                " ".repeat(from),
+                "^".repeat(to.saturating_sub(from).max(1)),
+                match label {
+                    Some(label) if i == last => format!(" {label}"),
+                    _ => String::new(),
+                }
+            ));
+        }
+
+        out.join("\n")
     }
 
     pub fn empty() -> Self {
@@ -62,6 +129,30 @@ impl Spans {
             col_span: (0, 0),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.line_span.0 == 0
+    }
+
+    pub fn line(&self) -> usize {
+        self.line_span.0
+    }
+
+    pub fn col(&self) -> usize {
+        self.col_span.0
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.line_span.1
+    }
+
+    pub fn end_col(&self) -> usize {
+        self.col_span.1
+    }
+
+    pub fn source(&self) -> &str {
+        &self.line
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -86,6 +177,11 @@ pub enum Node {
         rhs: Box<Node>,
         span: Spans,
     },
+    Modulo {
+        lhs: Box<Node>,
+        rhs: Box<Node>,
+        span: Spans,
+    },
 
     Integer {
         val: u64,
@@ -169,6 +265,11 @@ pub enum Node {
         value: Box<Node>,
         span: Spans,
     },
+    Native {
+        typed: String,
+        handle: NativeHandle,
+        span: Spans,
+    },
 
     Trait {
         name: String,
@@ -192,49 +293,34 @@ pub enum Node {
 
 impl Node {
     pub fn is_int(&self) -> bool {
-        match self {
-            Node::Integer { .. } => true,
-            _ => false,
-        }
+        matches!(self, Node::Integer { .. })
     }
 
     pub fn is_float(&self) -> bool {
-        match self {
-            Node::Float { .. } => true,
-            _ => false,
-        }
+        matches!(self, Node::Float { .. })
     }
 
     pub fn is_str(&self) -> bool {
-        match self {
-            Node::String { .. } => true,
-            _ => false,
-        }
+        matches!(self, Node::String { .. })
     }
 
     pub fn is_bool(&self) -> bool {
-        match self {
-            Node::Boolean { .. } => true,
-            _ => false,
-        }
+        matches!(self, Node::Boolean { .. })
     }
 
     pub fn is_id(&self) -> bool {
-        match self {
-            Node::Identifier { .. } => true,
-            _ => false,
-        }
+        matches!(self, Node::Identifier { .. })
     }
 
     pub fn is_op(&self) -> bool {
-        match &self {
-            Node::Add { .. } => true,
-            Node::Subtract { .. } => true,
-            Node::Multiply { .. } => true,
-            Node::Divide { .. } => true,
-
-            _ => false,
-        }
+        matches!(
+            self,
+            Node::Add { .. }
+                | Node::Subtract { .. }
+                | Node::Multiply { .. }
+                | Node::Divide { .. }
+                | Node::Modulo { .. }
+        )
     }
 
     pub fn inspect(&self) -> String {
@@ -246,6 +332,8 @@ impl Node {
             Node::Identifier { val, .. } => val.to_string(),
             Node::TypedVariable { name, .. } => name.to_string(),
             Node::Function { name, .. } => format!("<Function: {name}>"),
+            Node::BuiltinFn { name, .. } => format!("<Builtin Function: {name}>"),
+            Node::Native { typed, .. } => format!("<{typed}>"),
             Node::Object { typed, fields, .. } => {
                 format!(
                     "{typed} {{{}}}",
@@ -262,6 +350,7 @@ impl Node {
                 )
             }
 
+            Node::Empty => "none".to_string(),
             a => format!("<Internal: {:?}>", a.get_type()),
         }
     }
@@ -272,6 +361,7 @@ impl Node {
             Node::Subtract { span, .. } => span.clone(),
             Node::Multiply { span, .. } => span.clone(),
             Node::Divide { span, .. } => span.clone(),
+            Node::Modulo { span, .. } => span.clone(),
 
             Node::Integer { span, .. } => span.clone(),
             Node::Float { span, .. } => span.clone(),
@@ -292,6 +382,7 @@ impl Node {
             Node::Struct { span, .. } => span.clone(),
             Node::Object { span, .. } => span.clone(),
             Node::Field { span, .. } => span.clone(),
+            Node::Native { span, .. } => span.clone(),
 
             Node::Trait { span, .. } => span.clone(),
             Node::TraitImpl { span, .. } => span.clone(),
@@ -310,118 +401,57 @@ impl Node {
             Node::Boolean { .. } => "bool".to_string(),
             Node::TypedVariable { var_type, .. } => var_type.clone(),
             Node::Function { name, .. } => format!("<Function: '{}'>", name),
+            Node::Native { typed, .. } => typed.clone(),
+            Node::Object { typed, .. } => typed.clone(),
 
             _ => self.inspect(),
         }
     }
 
+    /// Approximate number of bytes this value keeps alive.
+    pub fn heap_size(&self) -> usize {
+        mem::size_of::<Node>()
+            + match self {
+                Node::String { val, .. } => val.capacity(),
+                Node::Object { fields, .. } => fields.iter().map(Node::heap_size).sum(),
+                Node::Field { value, .. } => value.heap_size(),
+                _ => 0,
+            }
+    }
+
     pub fn boxed(self) -> Box<Node> {
         Box::new(self)
     }
 
+    pub fn is_variadic(&self) -> bool {
+        matches!(self, Node::TypedVariable { var_type, .. } if var_type == VARIADIC)
+    }
+
     pub fn compare_type(&self, other: &Node) -> bool {
         match (self, other) {
-            (Node::TypedVariable { var_type, .. }, n) => var_type == &n.get_type(),
-            (n, Node::TypedVariable { var_type, .. }) => var_type == &n.get_type(),
+            (Node::TypedVariable { var_type, .. }, n)
+            | (n, Node::TypedVariable { var_type, .. }) => {
+                var_type == "any" || var_type == &n.get_type()
+            }
             _ => mem::discriminant(self) == mem::discriminant(other),
         }
     }
 }
 
 fn eval_expr(expr: Node, scope: &Scope) -> Result<Node, FangErr> {
-    match expr {
-        Node::Add { lhs, rhs, span } => {
-            let (a, b) = standardize_types(lhs, rhs, scope)?;
-            match (a, b) {
-                (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => Ok(Node::Integer {
-                    val: a + b,
-                    span: span.clone(),
-                }),
-                (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => Ok(Node::Float {
-                    val: a + b,
-                    span: span.clone(),
-                }),
-                (Node::String { val: a, .. }, Node::String { val: b, .. }) => Ok(Node::String {
-                    val: a + &b,
-                    span: span.clone(),
-                }),
-                (a, b) => Err(FangErr::OperationUnsupported {
-                    span,
-                    op: "add".to_string(),
-                    lhs: a.get_type(),
-                    rhs: b.get_type(),
-                    scope: scope.name.clone(),
-                }),
-            }
-        }
-
-        Node::Subtract { lhs, rhs, span } => {
-            let (a, b) = standardize_types(lhs, rhs, scope)?;
-            match (a, b) {
-                (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => Ok(Node::Integer {
-                    val: a - b,
-                    span: span.clone(),
-                }),
-                (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => Ok(Node::Float {
-                    val: a - b,
-                    span: span.clone(),
-                }),
-                (a, b) => Err(FangErr::OperationUnsupported {
-                    span,
-                    op: "subtract".to_string(),
-                    lhs: a.get_type(),
-                    rhs: b.get_type(),
-                    scope: scope.name.clone(),
-                }),
-            }
-        }
-
-        Node::Multiply { lhs, rhs, span } => {
-            let (a, b) = standardize_types(lhs, rhs, scope)?;
-            match (a, b) {
-                (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => Ok(Node::Integer {
-                    val: a * b,
-                    span: span.clone(),
-                }),
-                (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => Ok(Node::Float {
-                    val: a * b,
-                    span: span.clone(),
-                }),
-                (a, b) => Err(FangErr::OperationUnsupported {
-                    span,
-                    op: "multiply".to_string(),
-                    lhs: a.get_type(),
-                    rhs: b.get_type(),
-                    scope: scope.name.clone(),
-                }),
-            }
-        }
-
-        Node::Divide { lhs, rhs, span } => {
-            let (a, b) = standardize_types(lhs, rhs, scope)?;
-            match (a, b) {
-                (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => Ok(Node::Integer {
-                    val: a / b,
-                    span: span.clone(),
-                }),
-                (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => Ok(Node::Float {
-                    val: a / b,
-                    span: span.clone(),
-                }),
-                (a, b) => Err(FangErr::OperationUnsupported {
-                    span,
-                    op: "divide".to_string(),
-                    lhs: a.get_type(),
-                    rhs: b.get_type(),
-                    scope: scope.name.clone(),
-                }),
-            }
-        }
-
-        a => Ok(a),
-    }
+    let (op, lhs, rhs) = match expr {
+        Node::Add { lhs, rhs, span } => (Op::Add { span }, lhs, rhs),
+        Node::Subtract { lhs, rhs, span } => (Op::Subtract { span }, lhs, rhs),
+        Node::Multiply { lhs, rhs, span } => (Op::Multiply { span }, lhs, rhs),
+        Node::Divide { lhs, rhs, span } => (Op::Divide { span }, lhs, rhs),
+        Node::Modulo { lhs, rhs, span } => (Op::Modulo { span }, lhs, rhs),
+        a => return Ok(a),
+    };
+
+    arithmetic(&op, *lhs, *rhs, scope)
 }
 
+#[allow(clippy::boxed_local)]
 pub fn standardize_types(
     mut a: Box<Node>,
     mut b: Box<Node>,
@@ -446,7 +476,7 @@ pub fn standardize_types(
             None => {
                 return Err(FangErr::UndeclaredVariable {
                     span: b.span(),
-                    name: a.inspect(),
+                    name: b.inspect(),
                     scope: scope.name.clone(),
                 })
             }
@@ -454,11 +484,11 @@ pub fn standardize_types(
     }
 
     while a.is_op() {
-        a = Box::new(eval_expr(*a, scope)?);
+        *a = eval_expr(*a, scope)?;
     }
 
     while b.is_op() {
-        b = Box::new(eval_expr(*b, scope)?);
+        *b = eval_expr(*b, scope)?;
     }
 
     if a.is_str() || b.is_str() {
@@ -511,3 +541,31 @@ pub fn standardize_types(
         scope: scope.name.clone(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_line_snippet() {
+        let span = Spans::new("let b = \"x\" -\n    1;", ((1, 9), (2, 6)));
+        let snippet = span.label("here");
+        let lines = snippet.lines().skip(1).collect::<Vec<&str>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                "  |",
+                "1 | let b = \"x\" -",
+                "  |         ^^^^^",
+                "2 |     1;",
+                "  |     ^ here",
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_snippet_is_location_only() {
+        assert_eq!(Spans::empty().snippet().lines().count(), 1);
+    }
+}
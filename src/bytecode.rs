@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
 use crate::{
-    ast::{standardize_types, BuiltinFnBody, Node, Spans},
+    ast::{standardize_types, BuiltinFnBody, Node, Spans, LIST},
     errs::FangErr,
-    scope::Scope,
+    optimise::optimise,
+    scope::{Scope, TraitFn, Type},
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,6 +22,9 @@ pub enum Op {
     Divide {
         span: Spans,
     },
+    Modulo {
+        span: Spans,
+    },
     Multiply {
         span: Spans,
     },
@@ -32,13 +36,21 @@ pub enum Op {
     Declare {
         name: String,
         var_type: Option<String>,
+        span: Spans,
     },
     Load {
         name: String,
+        span: Spans,
     },
 
     Call {
         name: String,
+        argc: usize,
+        span: Spans,
+    },
+    TailCall {
+        name: String,
+        argc: usize,
         span: Spans,
     },
     Function {
@@ -53,6 +65,21 @@ pub enum Op {
         body: BuiltinFnBody,
     },
 
+    Object {
+        typed: String,
+        names: Vec<String>,
+        span: Spans,
+    },
+    Struct {
+        name: String,
+        fields: Vec<Node>,
+        span: Spans,
+    },
+    Trait {
+        name: String,
+        fields: Vec<Node>,
+        span: Spans,
+    },
     ImplTrait {
         trait_name: String,
         type_name: String,
@@ -63,6 +90,33 @@ pub enum Op {
     Return,
 }
 
+impl Op {
+    pub fn span(&self) -> Option<Spans> {
+        let span = match self {
+            Op::Push { value } => value.span(),
+            Op::Add { span } => span.clone(),
+            Op::Subtract { span } => span.clone(),
+            Op::Divide { span } => span.clone(),
+            Op::Modulo { span } => span.clone(),
+            Op::Multiply { span } => span.clone(),
+            Op::Assign { span, .. } => span.clone(),
+            Op::Declare { span, .. } => span.clone(),
+            Op::Load { span, .. } => span.clone(),
+            Op::Call { span, .. } => span.clone(),
+            Op::TailCall { span, .. } => span.clone(),
+            Op::Function { span, .. } => span.clone(),
+            Op::Object { span, .. } => span.clone(),
+            Op::Struct { span, .. } => span.clone(),
+            Op::Trait { span, .. } => span.clone(),
+            Op::ImplTrait { span, .. } => span.clone(),
+
+            Op::BuiltinCall { .. } | Op::Return => return None,
+        };
+
+        (!span.is_empty()).then_some(span)
+    }
+}
+
 pub fn ast_to_bytecode(node: Node, ops: &mut Vec<Op>) {
     match node {
         Node::Add { lhs, rhs, span } => {
@@ -85,36 +139,52 @@ pub fn ast_to_bytecode(node: Node, ops: &mut Vec<Op>) {
             ast_to_bytecode(*lhs, ops);
             ops.push(Op::Divide { span });
         }
+        Node::Modulo { lhs, rhs, span } => {
+            ast_to_bytecode(*rhs, ops);
+            ast_to_bytecode(*lhs, ops);
+            ops.push(Op::Modulo { span });
+        }
         Node::Declaration {
             name,
             var_type,
             rhs,
-            ..
+            span,
         } => {
             if let Some(rhs) = rhs {
                 ast_to_bytecode(*rhs, ops);
             }
 
-            ops.push(Op::Declare { name, var_type });
+            ops.push(Op::Declare {
+                name,
+                var_type,
+                span,
+            });
         }
         Node::Assignment { name, rhs, span } => {
             ast_to_bytecode(*rhs, ops);
             ops.push(Op::Assign { name, span });
         }
-        Node::Identifier { val, .. } => {
-            ops.push(Op::Load { name: val });
+        Node::Identifier { val, span } => {
+            ops.push(Op::Load { name: val, span });
         }
         Node::Object {
             fields,
             typed,
             span,
-        } => ops.push(Op::Push {
-            value: Node::Object {
-                fields,
-                typed,
-                span,
-            },
-        }),
+        } => {
+            let mut names = Vec::with_capacity(fields.len());
+            for field in fields.into_iter().rev() {
+                match field {
+                    Node::Field { name, value, .. } => {
+                        ast_to_bytecode(*value, ops);
+                        names.push(name);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            names.reverse();
+            ops.push(Op::Object { typed, names, span });
+        }
         Node::Function {
             name,
             args,
@@ -134,10 +204,31 @@ pub fn ast_to_bytecode(node: Node, ops: &mut Vec<Op>) {
             for arg in args.iter().rev() {
                 ast_to_bytecode(arg.clone(), ops);
             }
-            ops.push(Op::Call { name, span });
+            ops.push(Op::Call {
+                name,
+                argc: args.len(),
+                span,
+            });
+        }
+        Node::BuiltinFn { body, .. } => {
+            ops.push(Op::BuiltinCall { body });
+            ops.push(Op::Return);
         }
-        Node::BuiltinFn { body, .. } => ops.push(Op::BuiltinCall { body }),
 
+        Node::Struct { name, fields, span } => {
+            ops.push(Op::Struct {
+                name,
+                fields: *fields,
+                span,
+            });
+        }
+        Node::Trait { name, fields, span } => {
+            ops.push(Op::Trait {
+                name,
+                fields: *fields,
+                span,
+            });
+        }
         Node::TraitImpl {
             trait_name,
             type_name,
@@ -152,160 +243,252 @@ pub fn ast_to_bytecode(node: Node, ops: &mut Vec<Op>) {
             });
         }
 
-        Node::Return { value, .. } => {
-            ast_to_bytecode(*value, ops);
-            ops.push(Op::Return);
-        }
+        Node::Return { value, .. } => match *value {
+            Node::Call { name, args, span } => {
+                for arg in args.iter().rev() {
+                    ast_to_bytecode(arg.clone(), ops);
+                }
+                ops.push(Op::TailCall {
+                    name,
+                    argc: args.len(),
+                    span,
+                });
+            }
+            value => {
+                ast_to_bytecode(value, ops);
+                ops.push(Op::Return);
+            }
+        },
 
         Node::Empty => (),
         literal => ops.push(Op::Push { value: literal }),
     }
 }
 
-pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>, FangErr> {
+/// Applies an arithmetic op to its operands after coercing them with `standardize_types`.
+///
+/// Integer arithmetic is checked: dividing or taking the modulo by zero raises
+/// `DivisionByZero`, and results that don't fit an `int` raise `IntegerOverflow`.
+/// Float arithmetic follows IEEE 754 and never errors, so `1.0 / 0` is `inf`,
+/// `0.0 / 0` and `x % 0.0` are `NaN`, and `NaN` compares unequal to everything.
+pub fn arithmetic(op: &Op, lhs: Node, rhs: Node, scope: &Scope) -> Result<Node, FangErr> {
+    type IntOp = fn(u64, u64) -> Option<u64>;
+    type FloatOp = fn(f64, f64) -> f64;
+
+    let (span, int, float): (&Spans, IntOp, FloatOp) = match op {
+        Op::Add { span } => (span, u64::checked_add, |a, b| a + b),
+        Op::Subtract { span } => (span, u64::checked_sub, |a, b| a - b),
+        Op::Multiply { span } => (span, u64::checked_mul, |a, b| a * b),
+        Op::Divide { span } => (span, u64::checked_div, |a, b| a / b),
+        Op::Modulo { span } => (span, u64::checked_rem, |a, b| a % b),
+        _ => unreachable!(),
+    };
+    let name = op_name(op);
+
+    // `none` would otherwise be taken for a string when added to one.
+    if matches!(lhs, Node::Empty) || matches!(rhs, Node::Empty) {
+        return Err(FangErr::OperationUnsupported {
+            span: span.clone(),
+            op: name.to_string(),
+            lhs: lhs.inspect(),
+            rhs: rhs.inspect(),
+            scope: scope.name.clone(),
+        });
+    }
+
+    match standardize_types(lhs.boxed(), rhs.boxed(), scope)? {
+        (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => match int(a, b) {
+            Some(val) => Ok(Node::Integer {
+                val,
+                span: span.clone(),
+            }),
+            None if b == 0 => Err(FangErr::DivisionByZero {
+                span: span.clone(),
+                op: name.to_string(),
+                lhs: a.to_string(),
+                scope: scope.name.clone(),
+            }),
+            None => Err(FangErr::IntegerOverflow {
+                span: span.clone(),
+                op: name.to_string(),
+                lhs: a.to_string(),
+                rhs: b.to_string(),
+                scope: scope.name.clone(),
+            }),
+        },
+        (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => Ok(Node::Float {
+            val: float(a, b),
+            span: span.clone(),
+        }),
+        (Node::String { val: a, .. }, Node::String { val: b, .. }) if name == "add" => {
+            Ok(Node::String {
+                val: a + &b,
+                span: span.clone(),
+            })
+        }
+        (a, b) => Err(FangErr::OperationUnsupported {
+            span: span.clone(),
+            op: name.to_string(),
+            lhs: a.inspect(),
+            rhs: b.inspect(),
+            scope: scope.name.clone(),
+        }),
+    }
+}
+
+fn op_name(op: &Op) -> &'static str {
+    match op {
+        Op::Add { .. } => "add",
+        Op::Subtract { .. } => "subtract",
+        Op::Multiply { .. } => "multiply",
+        Op::Divide { .. } => "divide",
+        Op::Modulo { .. } => "modulo",
+        _ => unreachable!(),
+    }
+}
+
+// Takes the value `op` works on. Calls always leave one, so the stack only runs dry when
+// something that isn't an expression, like a trait declaration, is used as a value.
+fn pop(
+    stack: &mut Vec<Node>,
+    op: &Op,
+    what: impl FnOnce() -> String,
+    scope: &Scope,
+) -> Result<Node, FangErr> {
+    stack.pop().ok_or_else(|| FangErr::MissingValue {
+        span: op.span().unwrap_or_else(Spans::empty),
+        op: what(),
+        scope: scope.name.clone(),
+    })
+}
+
+/// Compiles top level statements, optimising them if `scope` asks for it.
+pub fn compile(ast: Vec<Node>, scope: &Scope) -> Vec<Op> {
     let mut ops = Vec::new();
 
     for node in ast {
         ast_to_bytecode(node, &mut ops);
     }
 
+    if scope.optimise {
+        ops = optimise(ops);
+    }
+
+    ops
+}
+
+fn pop_args(
+    name: &str,
+    argc: usize,
+    span: &Spans,
+    stack: &mut Vec<Node>,
+    scope: &Scope,
+) -> Result<Vec<Node>, FangErr> {
+    let args = scope
+        .get_args(name)
+        .ok_or_else(|| FangErr::UndeclaredFunction {
+            span: span.clone(),
+            name: name.to_string(),
+            scope: scope.name.clone(),
+        })?;
+    let variadic = args.last().is_some_and(Node::is_variadic);
+    let fixed = args.len() - variadic as usize;
+    if argc != args.len() && !(variadic && argc >= fixed) {
+        return Err(FangErr::ArgumentLengthMismatch {
+            span: span.clone(),
+            expected: fixed,
+            found: argc,
+            scope: scope.name.clone(),
+        });
+    }
+
+    // The stack only runs short if the call's `argc` counted an argument that pushed nothing.
+    let mut pop = |found: usize| {
+        stack.pop().ok_or_else(|| FangErr::ArgumentLengthMismatch {
+            span: span.clone(),
+            expected: argc,
+            found,
+            scope: scope.name.clone(),
+        })
+    };
+
+    let mut props = Vec::<Node>::new();
+    for arg in &args[..fixed] {
+        let prop = pop(props.len())?;
+
+        if !prop.compare_type(arg) {
+            return Err(FangErr::TypeMismatch {
+                span: span.clone(),
+                expected: arg.get_type(),
+                found: prop.get_type(),
+                scope: scope.name.clone(),
+            });
+        }
+
+        props.push(prop);
+    }
+
+    // The rest are gathered into a list.
+    if variadic {
+        let rest = (0..argc - fixed)
+            .map(|i| {
+                Ok(Node::Field {
+                    name: i.to_string(),
+                    value: Box::new(pop(fixed + i)?),
+                    span: Spans::empty(),
+                })
+            })
+            .collect::<Result<Vec<Node>, FangErr>>()?;
+        props.push(Node::Object {
+            typed: LIST.to_string(),
+            fields: Box::new(rest),
+            span: span.clone(),
+        });
+    }
+
+    Ok(props)
+}
+
+pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>, FangErr> {
+    let mut ops = compile(ast, scope);
+
     let mut stack = Vec::<Node>::new();
     let mut i = 0;
     while i < ops.len() {
+        scope.limits.tick(&ops[i], scope)?;
+
         match &ops[i] {
             Op::Push { value } => stack.push(value.clone()),
-            Op::Add { span } => {
-                let (a, b) = standardize_types(
-                    stack.pop().unwrap().boxed(),
-                    stack.pop().unwrap().boxed(),
-                    scope,
-                )?;
-                match (a, b) {
-                    (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => {
-                        stack.push(Node::Integer {
-                            val: a + b,
-                            span: span.clone(),
-                        })
-                    }
-                    (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => {
-                        stack.push(Node::Float {
-                            val: a + b,
-                            span: span.clone(),
-                        })
-                    }
-                    (Node::String { val: a, .. }, Node::String { val: b, .. }) => {
-                        stack.push(Node::String {
-                            val: a + &b,
-                            span: span.clone(),
-                        })
-                    }
-                    (a, b) => {
-                        return Err(FangErr::OperationUnsupported {
-                            op: "add".to_string(),
-                            lhs: a.inspect(),
-                            rhs: b.inspect(),
-                            scope: scope.name.clone(),
-                        })
-                    }
-                }
-            }
-            Op::Subtract { span } => {
-                let (a, b) = standardize_types(
-                    stack.pop().unwrap().boxed(),
-                    stack.pop().unwrap().boxed(),
-                    scope,
-                )?;
-                match (a, b) {
-                    (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => {
-                        stack.push(Node::Integer {
-                            val: a - b,
-                            span: span.clone(),
-                        })
-                    }
-                    (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => {
-                        stack.push(Node::Float {
-                            val: a - b,
-                            span: span.clone(),
-                        })
-                    }
-                    (a, b) => {
-                        return Err(FangErr::OperationUnsupported {
-                            op: "subtract".to_string(),
-                            lhs: a.inspect(),
-                            rhs: b.inspect(),
-                            scope: scope.name.clone(),
-                        })
-                    }
-                }
+            op @ (Op::Add { .. }
+            | Op::Subtract { .. }
+            | Op::Multiply { .. }
+            | Op::Divide { .. }
+            | Op::Modulo { .. }) => {
+                let a = pop(&mut stack, op, || op_name(op).to_string(), scope)?;
+                let b = pop(&mut stack, op, || op_name(op).to_string(), scope)?;
+                stack.push(arithmetic(op, a, b, scope)?);
             }
-            Op::Multiply { span } => {
-                let (a, b) = standardize_types(
-                    stack.pop().unwrap().boxed(),
-                    stack.pop().unwrap().boxed(),
-                    scope,
-                )?;
-                match (a, b) {
-                    (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => {
-                        stack.push(Node::Integer {
-                            val: a * b,
-                            span: span.clone(),
-                        })
-                    }
-                    (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => {
-                        stack.push(Node::Float {
-                            val: a * b,
-                            span: span.clone(),
-                        })
-                    }
-                    (a, b) => {
-                        return Err(FangErr::OperationUnsupported {
-                            op: "multiply".to_string(),
-                            lhs: a.inspect(),
-                            rhs: b.inspect(),
-                            scope: scope.name.clone(),
-                        })
-                    }
-                }
+            Op::Assign { name, span } => {
+                let val = pop(&mut stack, &ops[i], || format!("assign to {name}"), scope)?;
+                scope.assign(name.clone(), val, span)?;
+                scope.limits.check_heap(&ops[i], scope, &stack)?;
             }
-            Op::Divide { span } => {
-                let (a, b) = standardize_types(
-                    stack.pop().unwrap().boxed(),
-                    stack.pop().unwrap().boxed(),
+            Op::Declare {
+                name,
+                var_type,
+                span,
+            } => {
+                let val = pop(
+                    &mut stack,
+                    &ops[i],
+                    || format!("declare {name} with"),
                     scope,
                 )?;
-                match (a, b) {
-                    (Node::Integer { val: a, .. }, Node::Integer { val: b, .. }) => {
-                        stack.push(Node::Integer {
-                            val: a.clone() / b.clone(),
-                            span: span.clone(),
-                        })
-                    }
-                    (Node::Float { val: a, .. }, Node::Float { val: b, .. }) => {
-                        stack.push(Node::Float {
-                            val: a.clone() / b.clone(),
-                            span: span.clone(),
-                        })
-                    }
-                    (a, b) => {
-                        return Err(FangErr::OperationUnsupported {
-                            op: "divide".to_string(),
-                            lhs: a.inspect(),
-                            rhs: b.inspect(),
-                            scope: scope.name.clone(),
-                        })
-                    }
-                }
-            }
-            Op::Assign { name, .. } => {
-                let val = stack.pop().unwrap();
-                scope.assign(name.clone(), val)?;
-            }
-            Op::Declare { name, var_type } => {
-                let val = stack.pop().unwrap();
 
                 if let Some(t) = var_type {
                     if *t != val.get_type() {
                         return Err(FangErr::TypeMismatch {
+                            span: span.clone(),
                             expected: t.clone(),
                             found: val.get_type(),
                             scope: scope.name.clone(),
@@ -313,13 +496,15 @@ pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>,
                     }
                 }
 
-                scope.declare(name.clone(), val)?;
+                scope.declare(name.clone(), val, span)?;
+                scope.limits.check_heap(&ops[i], scope, &stack)?;
             }
-            Op::Load { name } => {
-                if let Some(value) = scope.get(&name) {
+            Op::Load { name, span } => {
+                if let Some(value) = scope.get(name) {
                     stack.push(value.clone());
                 } else {
                     return Err(FangErr::UndeclaredVariable {
+                        span: span.clone(),
                         name: name.clone(),
                         scope: scope.name.clone(),
                     });
@@ -330,45 +515,59 @@ pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>,
                 args,
                 body,
                 return_type,
-                ..
+                span,
             } => {
                 scope.put_fn(
                     name.clone(),
                     args.clone(),
                     body.clone(),
                     return_type.clone(),
+                    span,
                 )?;
             }
-            Op::Call { name , ..} => {
-                let args = scope.get_args(&name).expect(&format!(
-                    "Function '{}' not found in scope {}",
-                    name, scope.name
-                ));
-                let mut props = Vec::<Node>::new();
-                for arg in args {
-                    let prop = stack
-                        .pop()
-                        .expect(&format!("Expected argument for function '{}'", name));
-
-                    if !prop.compare_type(&arg) {
-                        return Err(FangErr::TypeMismatch {
-                            expected: arg.get_type(),
-                            found: prop.get_type(),
-                            scope: scope.name.clone(),
-                        });
-                    }
-
-                    props.push(prop);
-                }
+            Op::Call { name, argc, span } => {
+                let _frame = scope.limits.enter(&ops[i], scope)?;
+                let props = pop_args(name, *argc, span, &mut stack, scope)?;
+                scope.limits.check_heap(&ops[i], scope, &props)?;
 
-                let insert = scope.call(&name, props)?;
+                let insert = scope
+                    .call(name, props, span)
+                    .map_err(|e| e.called_from(name, &scope.name, span))?;
                 ops = [ops[..=i].to_vec(), insert, ops[i + 1..].to_vec()]
                     .into_iter()
                     .flatten()
                     .collect::<Vec<Op>>();
             }
+            // A function returning a call to itself replaces its own frame rather than
+            // nesting another `eval_bytecode`, so self-recursion runs in constant stack
+            // (and, like any loop, leaves nothing behind in a stack trace).
+            Op::TailCall { name, argc, span } if *name == scope.name => {
+                let props = pop_args(name, *argc, span, &mut stack, scope)?;
+                let (frame, body) = scope.frame(name, scope.parent.clone(), props, span)?;
+
+                *scope = frame;
+                scope.limits.check_heap(&ops[i], scope, &[])?;
+
+                ops = compile(body, scope);
+                stack.clear();
+                i = 0;
+                continue;
+            }
+            Op::TailCall { name, argc, span } => {
+                let _frame = scope.limits.enter(&ops[i], scope)?;
+                let props = pop_args(name, *argc, span, &mut stack, scope)?;
+                scope.limits.check_heap(&ops[i], scope, &props)?;
+
+                let insert = scope
+                    .call(name, props, span)
+                    .map_err(|e| e.called_from(name, &scope.name, span))?;
+                ops = [ops[..=i].to_vec(), insert, vec![Op::Return]]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<Op>>();
+            }
             Op::BuiltinCall { body } => {
-                if let Some(val) = body.0(scope) {
+                if let Some(val) = body.0(scope)? {
                     stack.push(val);
                 };
             }
@@ -376,11 +575,83 @@ pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>,
             Op::Return => {
                 return Ok(stack.pop());
             }
+            Op::Object { typed, names, span } => {
+                let mut fields = Vec::with_capacity(names.len());
+                for name in names {
+                    let value = pop(
+                        &mut stack,
+                        &ops[i],
+                        || format!("set {typed}.{name} to"),
+                        scope,
+                    )?;
+                    fields.push(Node::Field {
+                        name: name.clone(),
+                        value: Box::new(value),
+                        span: Spans::empty(),
+                    });
+                }
+                if let Some(ty @ Type::Struct { .. }) = scope.get_type(typed) {
+                    ty.validate_struct(typed, &fields, span)?;
+                }
+
+                stack.push(Node::Object {
+                    typed: typed.clone(),
+                    fields: Box::new(fields),
+                    span: span.clone(),
+                });
+                scope.limits.check_heap(&ops[i], scope, &stack)?;
+            }
+            Op::Struct { name, fields, span } => {
+                scope.define_struct(name.clone(), fields.clone(), span)?;
+            }
+            Op::Trait { name, fields, span } => {
+                let functions = fields
+                    .iter()
+                    .map(|f| match f {
+                        Node::Function {
+                            name,
+                            args,
+                            body,
+                            return_type,
+                            ..
+                        } => Ok((
+                            name.clone(),
+                            TraitFn::Default {
+                                name: name.clone(),
+                                args: *args.clone(),
+                                body: *body.clone(),
+                                return_type: return_type.clone(),
+                            },
+                        )),
+                        Node::FunctionOutline {
+                            name,
+                            args,
+                            return_type,
+                            ..
+                        } => Ok((
+                            name.clone(),
+                            TraitFn::NoBody {
+                                name: name.clone(),
+                                args: *args.clone(),
+                                return_type: return_type.clone(),
+                            },
+                        )),
+                        _ => Err(FangErr::UnexpectedToken {
+                            span: f.span(),
+                            expected: "Function".to_string(),
+                            found: f.inspect(),
+                            scope: scope.name.clone(),
+                        }),
+                    })
+                    .collect::<Result<HashMap<String, TraitFn>, FangErr>>()?;
+
+                scope.define_trait(name.clone(), functions, span)?;
+            }
             Op::ImplTrait {
                 trait_name,
                 type_name,
                 fields,
-                ..
+                span,
             } => {
                 let mut m = HashMap::new();
                 fields
@@ -400,17 +671,16 @@ pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>,
 
                             Ok(())
                         }
-                        _ => {
-                            return Err(FangErr::UnexpectedToken {
-                                expected: "Function".to_string(),
-                                found: f.inspect(),
-                                scope: scope.name.clone(),
-                            })
-                        }
+                        _ => Err(FangErr::UnexpectedToken {
+                            span: f.span(),
+                            expected: "Function".to_string(),
+                            found: f.inspect(),
+                            scope: scope.name.clone(),
+                        }),
                     })
                     .collect::<Result<Vec<_>, FangErr>>()?;
 
-                scope.implement(trait_name.clone(), type_name.clone(), m)?;
+                scope.implement(type_name.clone(), trait_name.clone(), m, span)?;
             }
         }
 
@@ -420,3 +690,151 @@ pub fn eval_bytecode(ast: Vec<Node>, scope: &mut Scope) -> Result<Option<Node>,
     // assert!(stack.is_empty());
     Ok(None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scope::global_scope, testing::parse};
+
+    fn eval(expr: &str) -> Result<Node, FangErr> {
+        let mut scope = global_scope();
+        eval_bytecode(parse(&format!("let r = {expr};")), &mut scope)?;
+
+        Ok(scope.get("r").unwrap())
+    }
+
+    fn int(expr: &str) -> u64 {
+        match eval(expr) {
+            Ok(Node::Integer { val, .. }) => val,
+            res => panic!("{expr} gave {res:?}"),
+        }
+    }
+
+    fn float(expr: &str) -> f64 {
+        match eval(expr) {
+            Ok(Node::Float { val, .. }) => val,
+            res => panic!("{expr} gave {res:?}"),
+        }
+    }
+
+    #[test]
+    fn add() {
+        assert_eq!(int("2 + 3"), 5);
+        assert_eq!(float("1.5 + 2"), 3.5);
+        assert!(matches!(eval(r#""a" + 1"#), Ok(Node::String { val, .. }) if val == "a1"));
+        assert!(matches!(
+            eval("18446744073709551615 + 1"),
+            Err(FangErr::IntegerOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn subtract() {
+        assert_eq!(int("5 - 3"), 2);
+        assert_eq!(float("1.5 - 2"), -0.5);
+        assert!(matches!(
+            eval("1 - 2"),
+            Err(FangErr::IntegerOverflow { .. })
+        ));
+        assert!(matches!(
+            eval(r#""a" - 1"#),
+            Err(FangErr::OperationUnsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn multiply() {
+        assert_eq!(int("6 * 7"), 42);
+        assert_eq!(float("1.5 * 2"), 3.0);
+        assert!(matches!(
+            eval("4294967296 * 4294967296"),
+            Err(FangErr::IntegerOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn divide() {
+        assert_eq!(int("7 / 2"), 3);
+        assert_eq!(float("7.0 / 2"), 3.5);
+        assert!(matches!(eval("7 / 0"), Err(FangErr::DivisionByZero { .. })));
+        assert!(matches!(
+            eval("7 / (1 - 1)"),
+            Err(FangErr::DivisionByZero { .. })
+        ));
+    }
+
+    #[test]
+    fn modulo() {
+        assert_eq!(int("7 % 2"), 1);
+        assert_eq!(float("7.5 % 2"), 1.5);
+        assert!(matches!(eval("7 % 0"), Err(FangErr::DivisionByZero { .. })));
+    }
+
+    #[test]
+    fn multiplicative_operators_share_a_level() {
+        assert_eq!(int("7 / 2 % 2"), 1);
+        assert_eq!(int("7 % 4 / 2"), 1);
+        assert_eq!(int("2 * 3 / 2"), 3);
+        assert_eq!(int("1 + 6 % 4 * 3"), 7);
+    }
+
+    #[test]
+    fn float_division_by_zero() {
+        assert_eq!(float("1.0 / 0"), f64::INFINITY);
+        assert_eq!(float("(0.0 - 1) / 0"), f64::NEG_INFINITY);
+        assert!(float("0.0 / 0").is_nan());
+        assert!(float("1.0 % 0").is_nan());
+    }
+
+    #[test]
+    fn errors_carry_the_call_stack() {
+        let mut scope = global_scope();
+        let err = eval_bytecode(
+            parse(
+                "fn inner(x: int): int { return x / 0; }\n\
+                 fn outer(x: int): int { let y = inner(x); return y; }\n\
+                 let r = outer(1);",
+            ),
+            &mut scope,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.kind(), FangErr::DivisionByZero { .. }));
+        assert_eq!(
+            err.trace()
+                .iter()
+                .map(|c| (c.caller.as_str(), c.name.as_str(), c.span.line()))
+                .collect::<Vec<_>>(),
+            vec![("<Fang>", "outer", 3), ("outer", "inner", 2)]
+        );
+    }
+
+    #[test]
+    fn exit_unwinds_without_a_trace() {
+        let mut scope = global_scope();
+        let err = eval_bytecode(
+            parse(
+                "fn stop(): int { exit(260); return 1; }
+let r = stop();
+let s = 2;",
+            ),
+            &mut scope,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, FangErr::Exit { code: 4, .. }));
+        assert_eq!(err.exit_code(), 4);
+        assert!(scope.get("s").is_none());
+    }
+
+    #[test]
+    fn arguments_that_push_nothing_are_an_error() {
+        let call = Node::Call {
+            name: "int".to_string(),
+            args: Box::new(vec![Node::Empty]),
+            span: Spans::empty(),
+        };
+        let res = eval_bytecode(vec![call], &mut global_scope());
+        assert!(matches!(res, Err(FangErr::ArgumentLengthMismatch { .. })));
+    }
+}
@@ -0,0 +1,213 @@
+pub const USAGE: &str = "\
+Usage: fang [command] [options] [<file> | - | -e <code>] [-- <script args>...]
+
+Commands:
+    run <file> [args...]   run a script, passing any further arguments to it (the default)
+    check <files...>       parse and lint without running
+    fmt [--check] <files...>
+                           reformat files in place, or report unformatted files with --check
+    ast <file>             print the parsed AST
+    disasm <file>          print the compiled bytecode
+    test [paths...]        run every `test_*` function in the given files or directories
+                           (defaults to ./tests)
+    explain [code]         describe an error code, or list them all
+    repl                   start an interactive session (the default without a file)
+
+Options:
+    -e <code>              evaluate <code> instead of reading a file
+    -                      read the source from stdin
+    -O                     enable constant folding and dead code elimination
+    --max-depth=N          limit nested calls (default 256)
+    --max-ops=N            limit the number of executed operations
+    --max-heap=BYTES       limit the memory held by values
+    --sandbox              only allow console, with at most 10M operations and 64MB of memory
+    --allow-cap=NAME, --deny-cap=NAME
+                           allow or deny a capability: console, fs, process, env or clock
+    --error-format=human|json
+    -A <lint>, --allow=<lint>
+    -D <lint>, --deny=<lint>
+                           allow or deny a lint: unused_variables, unused_functions,
+                           unused_parameters, unreachable_code, shadowing or unused_impls,
+                           or the groups `unused` (the unused_* lints) and `warnings` (all)
+
+Exit status:
+    0                      success, or the status given to `exit` or a top level `return`
+    1                      runtime error, failed tests or denied lints
+    2                      the source or command line couldn't be parsed
+    3                      type error";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Run,
+    Check,
+    Fmt,
+    Ast,
+    Disasm,
+    Test,
+    Explain,
+    Repl,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    /// Options in their `--name=value` / `-X` form, with `-D x` and `-A x` joined up.
+    pub flags: Vec<String>,
+    pub sources: Vec<Source>,
+    /// Arguments after the script for `run`, or the error code for `explain`.
+    pub args: Vec<String>,
+}
+
+impl Cli {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Cli, String> {
+        let mut args = args.into_iter().peekable();
+
+        let command = match args.peek().map(String::as_str) {
+            Some("run") => Some(Command::Run),
+            Some("check") => Some(Command::Check),
+            Some("fmt") => Some(Command::Fmt),
+            Some("ast") => Some(Command::Ast),
+            Some("disasm") => Some(Command::Disasm),
+            Some("test") => Some(Command::Test),
+            Some("explain") => Some(Command::Explain),
+            Some("repl") => Some(Command::Repl),
+            _ => None,
+        };
+        if command.is_some() {
+            args.next();
+        }
+
+        let mut cli = Cli {
+            command: command.unwrap_or(Command::Run),
+            flags: vec![],
+            sources: vec![],
+            args: vec![],
+        };
+
+        while let Some(arg) = args.next() {
+            // Everything after the script belongs to the script.
+            if cli.command == Command::Run && !cli.sources.is_empty() {
+                if arg != "--" || !cli.args.is_empty() {
+                    cli.args.push(arg);
+                }
+                continue;
+            }
+
+            match arg.as_str() {
+                "--" => {
+                    if cli.command == Command::Run && cli.sources.is_empty() {
+                        if let Some(path) = args.next() {
+                            cli.sources.push(Source::File(path));
+                        }
+                    }
+                    cli.args.extend(args.by_ref());
+                }
+                "-" => cli.sources.push(Source::Stdin),
+                "-e" => {
+                    let code = args.next().ok_or("-e expects the code to run")?;
+                    cli.sources.push(Source::Inline(code));
+                }
+                "-D" | "-A" => {
+                    let lint = args.next().ok_or(format!("{arg} expects a lint name"))?;
+                    cli.flags.push(format!("{arg}{lint}"));
+                }
+                f if f.starts_with('-') => cli.flags.push(arg),
+                _ if cli.command == Command::Explain => cli.args.push(arg),
+                _ => cli.sources.push(Source::File(arg)),
+            }
+        }
+
+        if command.is_none() && cli.sources.is_empty() {
+            cli.command = Command::Repl;
+        }
+
+        match cli.command {
+            Command::Run | Command::Ast | Command::Disasm if cli.sources.len() != 1 => {
+                Err("Expected a single source file, `-` or `-e <code>`".to_string())
+            }
+            Command::Check | Command::Fmt if cli.sources.is_empty() => {
+                Err("Expected at least one source file".to_string())
+            }
+            _ => Ok(cli),
+        }
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &str) -> Result<Cli, String> {
+        Cli::parse(args.split_whitespace().map(String::from))
+    }
+
+    fn strings(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(cli("").unwrap().command, Command::Repl);
+        assert_eq!(cli("-O").unwrap().command, Command::Repl);
+
+        let c = cli("main.fg").unwrap();
+        assert_eq!(c.command, Command::Run);
+        assert_eq!(c.sources, vec![Source::File("main.fg".to_string())]);
+    }
+
+    #[test]
+    fn script_args_pass_through() {
+        let c = cli("run -O main.fg -O --max-ops=1 x").unwrap();
+        assert_eq!(c.flags, strings(&["-O"]));
+        assert_eq!(c.args, strings(&["-O", "--max-ops=1", "x"]));
+
+        let c = cli("-- -main.fg a").unwrap();
+        assert_eq!(c.sources, vec![Source::File("-main.fg".to_string())]);
+        assert_eq!(c.args, strings(&["a"]));
+
+        assert_eq!(cli("main.fg -- a").unwrap().args, strings(&["a"]));
+    }
+
+    #[test]
+    fn sources() {
+        assert_eq!(cli("-").unwrap().sources, vec![Source::Stdin]);
+        assert_eq!(
+            Cli::parse(strings(&["-e", "let a = 1;"])).unwrap().sources,
+            vec![Source::Inline("let a = 1;".to_string())]
+        );
+        assert_eq!(cli("check a.fg b.fg").unwrap().sources.len(), 2);
+        assert!(cli("ast").is_err());
+        assert!(cli("run a.fg").is_ok());
+    }
+
+    #[test]
+    fn lint_flags_take_a_value() {
+        let c = cli("check -D warnings -A unused a.fg").unwrap();
+        assert_eq!(c.flags, strings(&["-Dwarnings", "-Aunused"]));
+    }
+
+    #[test]
+    fn explain_takes_a_code() {
+        let c = cli("explain F0001").unwrap();
+        assert_eq!(c.command, Command::Explain);
+        assert_eq!(c.args, strings(&["F0001"]));
+    }
+
+    #[test]
+    fn usage_lists_every_lint() {
+        for lint in fang::lint::LINTS {
+            assert!(USAGE.contains(lint.name()), "{}", lint.name());
+        }
+    }
+}
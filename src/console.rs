@@ -0,0 +1,134 @@
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::{ast::Node, errs::FangErr, scope::Scope};
+
+/// How important a line of script output is, which decides the stream it goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Log,
+    Info,
+    Warn,
+    Error,
+    Debug,
+}
+
+impl Level {
+    /// Whether the level goes to stderr rather than stdout.
+    pub fn is_error(self) -> bool {
+        matches!(self, Level::Warn | Level::Error)
+    }
+}
+
+/// Somewhere script output is written.
+pub trait Sink {
+    fn write(&mut self, level: Level, text: &str);
+}
+
+/// Writes to stdout, or stderr for warnings and errors.
+pub struct Std;
+
+impl Sink for Std {
+    fn write(&mut self, level: Level, text: &str) {
+        // Output a script can't write anywhere is dropped rather than failing the script.
+        let _ = match level.is_error() {
+            true => io::stderr().write_all(text.as_bytes()),
+            false => {
+                let mut out = io::stdout();
+                out.write_all(text.as_bytes()).and_then(|_| out.flush())
+            }
+        };
+    }
+}
+
+/// Keeps output in memory, for hosts that want what a script printed.
+#[derive(Debug, Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<(Level, String)>>>);
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, with its level.
+    pub fn entries(&self) -> Vec<(Level, String)> {
+        self.0.borrow().clone()
+    }
+
+    /// Everything written so far at any level, in order.
+    pub fn text(&self) -> String {
+        self.0.borrow().iter().map(|(_, t)| t.as_str()).collect()
+    }
+}
+
+impl Sink for Capture {
+    fn write(&mut self, level: Level, text: &str) {
+        self.0.borrow_mut().push((level, text.to_string()));
+    }
+}
+
+/// The sink a scope's output goes to, shared by every scope of a run.
+#[derive(Clone)]
+pub struct Output(Rc<RefCell<dyn Sink>>);
+
+impl Output {
+    pub fn new(sink: impl Sink + 'static) -> Self {
+        Self(Rc::new(RefCell::new(sink)))
+    }
+
+    pub fn write(&self, level: Level, text: &str) {
+        self.0.borrow_mut().write(level, text);
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new(Std)
+    }
+}
+
+impl Debug for Output {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "<Output>")
+    }
+}
+
+/// Writes the `values` a builtin was called with, separated by spaces and followed by `end`.
+pub(crate) fn emit(scope: &Scope, level: Level, end: &str) -> Result<Option<Node>, FangErr> {
+    let text = match scope.get("values") {
+        Some(Node::Object { fields, .. }) => fields
+            .iter()
+            .map(|f| match f {
+                Node::Field { value, .. } => value.inspect(),
+                f => f.inspect(),
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+        _ => String::new(),
+    };
+
+    scope.output.write(level, &format!("{text}{end}"));
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    #[test]
+    fn output_goes_to_the_sink() {
+        let capture = Capture::new();
+        let mut engine = Engine::new().with_output(capture.clone());
+        engine
+            .eval("console.log(\"sum\", 1 + 2, true);\nconsole.warn(1.5);\nprint(\"a\");\nprintln(\"b\");\nconsole.info();")
+            .unwrap();
+
+        assert_eq!(capture.text(), "sum 3 true\n1.5\nab\n\n");
+        assert_eq!(capture.entries()[1], (Level::Warn, "1.5\n".to_string()));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    ast::Node,
+    bytecode::{ast_to_bytecode, Op},
+    optimise::optimise,
+};
+
+struct Section {
+    label: String,
+    header: String,
+    ops: Vec<Op>,
+}
+
+pub fn disassemble(ast: Vec<Node>, optimised: bool) -> String {
+    let mut sections = Vec::new();
+    collect(
+        "<main>".to_string(),
+        "<main>".to_string(),
+        ast,
+        optimised,
+        &mut sections,
+    );
+
+    let labels = sections
+        .iter()
+        .map(|s| s.label.clone())
+        .collect::<Vec<String>>();
+
+    sections
+        .iter()
+        .map(|s| render(s, &labels))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn collect(
+    label: String,
+    header: String,
+    body: Vec<Node>,
+    optimised: bool,
+    sections: &mut Vec<Section>,
+) {
+    let mut ops = Vec::new();
+    for node in body {
+        ast_to_bytecode(node, &mut ops);
+    }
+
+    if optimised {
+        ops = optimise(ops);
+    }
+
+    let index = sections.len();
+    sections.push(Section {
+        label,
+        header,
+        ops: Vec::new(),
+    });
+
+    for op in &ops {
+        match op {
+            Op::Function {
+                name,
+                args,
+                body,
+                return_type,
+                ..
+            } => collect(
+                unique_label(name.clone(), sections),
+                signature(name, args, return_type),
+                body.clone(),
+                optimised,
+                sections,
+            ),
+            Op::ImplTrait {
+                trait_name,
+                type_name,
+                fields,
+                ..
+            } => {
+                for field in fields {
+                    if let Node::Function {
+                        name,
+                        args,
+                        body,
+                        return_type,
+                        ..
+                    } = field
+                    {
+                        collect(
+                            unique_label(format!("{type_name}.{name}"), sections),
+                            format!(
+                                "impl {trait_name} for {type_name}: {}",
+                                signature(name, args, return_type)
+                            ),
+                            *body.clone(),
+                            optimised,
+                            sections,
+                        )
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    sections[index].ops = ops;
+}
+
+fn unique_label(label: String, sections: &[Section]) -> String {
+    let taken = |l: &str| sections.iter().any(|s| s.label == l);
+    if !taken(&label) {
+        return label;
+    }
+
+    (2..)
+        .map(|n| format!("{label}#{n}"))
+        .find(|l| !taken(l))
+        .unwrap()
+}
+
+fn signature(name: &str, args: &[Node], return_type: &Option<String>) -> String {
+    let args = args
+        .iter()
+        .map(|a| match a {
+            Node::TypedVariable { name, var_type, .. } if name == var_type => name.clone(),
+            Node::TypedVariable { name, var_type, .. } => format!("{name}: {var_type}"),
+            a => operand(a),
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    match return_type {
+        Some(rt) => format!("fn {name}({args}): {rt}"),
+        None => format!("fn {name}({args})"),
+    }
+}
+
+// Only a call naming a section exactly is linked to it. Which `Type.method` a method called
+// on a variable runs isn't known without running the script, so rather than guessing from
+// the method name, such calls are marked unresolved with the sections they could be.
+fn target(name: &str, labels: &[String]) -> String {
+    if labels.iter().any(|l| l == name) {
+        return format!("{name} -> @{name}");
+    }
+
+    let candidates = name.rsplit_once('.').map_or(vec![], |(_, method)| {
+        labels
+            .iter()
+            .filter(|l| l.rsplit_once('.').is_some_and(|(_, m)| m == method))
+            .map(|l| format!("@{l}"))
+            .collect()
+    });
+    match candidates[..] {
+        [] => name.to_string(),
+        _ => format!("{name} -> unresolved ({})", candidates.join(", ")),
+    }
+}
+
+fn operand(node: &Node) -> String {
+    match node {
+        Node::Integer { val, .. } => format!("int {val}"),
+        Node::Float { val, .. } => format!("float {val:?}"),
+        Node::String { val, .. } => format!("string {val:?}"),
+        Node::Boolean { val, .. } => format!("bool {val}"),
+        Node::Object { typed, fields, .. } => format!(
+            "object {typed} {{{}}}",
+            fields
+                .iter()
+                .map(|f| match f {
+                    Node::Field { name, .. } => name.clone(),
+                    f => operand(f),
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Node::Struct { name, .. } => format!("struct {name}"),
+        Node::Trait { name, .. } => format!("trait {name}"),
+        Node::Identifier { val, .. } => val.clone(),
+        Node::TypedVariable { name, var_type, .. } => format!("{name}: {var_type}"),
+        Node::Function { name, .. } => format!("fn {name}"),
+        Node::BuiltinFn { name, .. } => format!("builtin {name}"),
+        _ => "<node>".to_string(),
+    }
+}
+
+fn instruction(op: &Op, labels: &[String]) -> (&'static str, String) {
+    match op {
+        Op::Push { value } => ("push", operand(value)),
+        Op::Add { .. } => ("add", String::new()),
+        Op::Subtract { .. } => ("sub", String::new()),
+        Op::Multiply { .. } => ("mul", String::new()),
+        Op::Divide { .. } => ("div", String::new()),
+        Op::Modulo { .. } => ("mod", String::new()),
+        Op::Assign { name, .. } => ("assign", name.clone()),
+        Op::Declare {
+            name,
+            var_type: Some(t),
+            ..
+        } => ("declare", format!("{name}: {t}")),
+        Op::Declare { name, .. } => ("declare", name.clone()),
+        Op::Load { name, .. } => ("load", name.clone()),
+        Op::Call { name, .. } => ("call", target(name, labels)),
+        Op::TailCall { name, .. } => ("tailcall", target(name, labels)),
+        Op::Function { name, .. } => ("function", name.clone()),
+        Op::BuiltinCall { .. } => ("builtin", String::new()),
+        Op::Object { typed, names, .. } => ("object", format!("{typed} {{{}}}", names.join(", "))),
+        Op::Struct { name, .. } => ("struct", name.clone()),
+        Op::Trait { name, .. } => ("trait", name.clone()),
+        Op::ImplTrait {
+            trait_name,
+            type_name,
+            ..
+        } => ("impl", format!("{trait_name} for {type_name}")),
+        Op::Return => ("return", String::new()),
+    }
+}
+
+fn render(section: &Section, labels: &[String]) -> String {
+    let mut out = vec![format!("@{}: {}", section.label, section.header)];
+
+    let mut line = 0;
+    for (i, op) in section.ops.iter().enumerate() {
+        let span = op.span();
+        if let Some(span) = &span {
+            if span.line() != line {
+                line = span.line();
+                out.push(format!(
+                    "      ; {:>4} | {}",
+                    line,
+                    span.source().lines().next().unwrap_or("").trim_end()
+                ));
+            }
+        }
+
+        let (name, operands) = instruction(op, labels);
+        let location = span
+            .map(|s| format!("  ; {}:{}", s.line(), s.col()))
+            .unwrap_or_default();
+
+        out.push(
+            format!("  {i:04}  {name:<10}{operands:<32}{location}")
+                .trim_end()
+                .to_string(),
+        );
+    }
+
+    out.push(String::new());
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::parse;
+
+    fn listing(src: &str) -> String {
+        disassemble(parse(src), false)
+    }
+
+    #[test]
+    fn links_calls_to_their_sections() {
+        let out = listing(
+            "fn count(n: int): int { return count(n - 1); }\n\
+             impl Show for Point { fn show(self): string { return \"p\"; } };\n\
+             Point.show(1);",
+        );
+
+        assert!(out.contains("@count: fn count(n: int): int"), "{out}");
+        assert!(out.contains("tailcall  count -> @count"), "{out}");
+        assert!(out.contains("@Point.show: impl Show for Point: fn show(self): string"));
+        assert!(out.contains("call      Point.show -> @Point.show"), "{out}");
+    }
+
+    #[test]
+    fn method_calls_on_variables_are_unresolved() {
+        let out = listing(
+            "impl Show for Point { fn show(self): string { return \"p\"; } };\n\
+             impl Show for Circle { fn show(self): string { return \"c\"; } };\n\
+             p.show();\n\
+             console.log(\"x\");",
+        );
+
+        assert!(
+            out.contains("call      p.show -> unresolved (@Point.show, @Circle.show)"),
+            "{out}"
+        );
+        assert!(
+            out.lines()
+                .any(|l| l.contains("call      console.log") && !l.contains("->")),
+            "{out}"
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use std::{fs, path::Path, rc::Rc};
+
+use crate::{
+    ast::{Node, Spans},
+    bytecode::{compile, eval_bytecode, Op},
+    console::{Output, Sink},
+    errs::FangErr,
+    limits::Limits,
+    native::{native, FangType, FromFang, IntoArgs, NativeFn, NativeType},
+    parser::parse,
+    sandbox::Profile,
+    scope::{global_scope, Scope, Type},
+    value::Value,
+    FILE_NAME,
+};
+
+/// An interpreter with its own global scope, for running Fang from Rust.
+pub struct Engine {
+    scope: Scope,
+    file_name: String,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Statements that don't produce a value worth returning.
+fn is_expression(node: &Node) -> bool {
+    !matches!(
+        node,
+        Node::Declaration { .. }
+            | Node::Assignment { .. }
+            | Node::Function { .. }
+            | Node::TraitImpl { .. }
+            | Node::Struct { .. }
+            | Node::Trait { .. }
+            | Node::Return { .. }
+            | Node::Empty
+    )
+}
+
+/// Parses `src`, adding the trailing `;` that's easy to leave off a single expression.
+pub(crate) fn parse_lenient(src: &str) -> Result<Vec<Node>, Vec<FangErr>> {
+    parse(src).or_else(|errs| parse(&format!("{src};")).map_err(|_| errs))
+}
+
+/// Returns the value of the last statement from the top level, if it's an expression.
+pub(crate) fn return_last(mut ast: Vec<Node>) -> Vec<Node> {
+    if let Some(last) = ast.pop() {
+        ast.push(match is_expression(&last) {
+            true => Node::Return {
+                span: last.span(),
+                value: Box::new(last),
+            },
+            false => last,
+        });
+    }
+    ast
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            scope: global_scope(),
+            file_name: "<eval>".to_string(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.scope.limits = Rc::new(limits);
+        self
+    }
+
+    /// Applies the capabilities and limits of `profile`.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.scope.capabilities = profile.capabilities;
+        self.with_limits(profile.limits())
+    }
+
+    /// Sends what scripts print to `sink` instead of stdout and stderr.
+    pub fn with_output(mut self, sink: impl Sink + 'static) -> Self {
+        self.scope.output = Output::new(sink);
+        self
+    }
+
+    /// Enables constant folding and dead code elimination.
+    pub fn optimised(mut self, optimise: bool) -> Self {
+        self.scope.optimise = optimise;
+        self
+    }
+
+    /// Sets the file name used in the locations of errors from `parse` and `eval`.
+    pub fn with_file_name(mut self, name: &str) -> Self {
+        self.file_name = name.to_string();
+        self
+    }
+
+    /// Makes the engine's file name the one used in error locations.
+    pub(crate) fn locate(&self) {
+        *FILE_NAME.lock().unwrap() = self.file_name.clone();
+    }
+
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    pub fn scope_mut(&mut self) -> &mut Scope {
+        &mut self.scope
+    }
+
+    /// Parses `src`, reporting every syntax error found.
+    pub fn parse(&self, src: &str) -> Result<Vec<Node>, Vec<FangErr>> {
+        self.locate();
+        parse(src)
+    }
+
+    /// The bytecode `ast` runs as at the top level, optimised if the engine is.
+    pub fn compile(&self, ast: Vec<Node>) -> Vec<Op> {
+        compile(ast, &self.scope)
+    }
+
+    /// Runs `ast` in the global scope, returning the value of a top level `return`.
+    pub fn run(&mut self, ast: Vec<Node>) -> Result<Value, FangErr> {
+        self.locate();
+        let val = eval_bytecode(ast, &mut self.scope)?;
+        Ok(val.map_or(Value::None, Value::from_node))
+    }
+
+    /// Runs `src` in the global scope, returning the value of its last statement if that's an
+    /// expression. The final `;` is optional, and only the first syntax error is returned, use
+    /// `parse` to get all of them.
+    pub fn eval(&mut self, src: &str) -> Result<Value, FangErr> {
+        self.locate();
+        let ast = parse_lenient(src).map_err(|errs| errs.into_iter().next().unwrap())?;
+        self.run(return_last(ast))
+    }
+
+    /// Runs the script at `path`, returning the value of a top level `return`.
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, FangErr> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path).map_err(|e| FangErr::Io {
+            span: Spans::empty(),
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+
+        let previous = std::mem::replace(
+            &mut self.file_name,
+            path.file_name().map_or(path.display().to_string(), |n| {
+                n.to_string_lossy().to_string()
+            }),
+        );
+        let res = self
+            .parse(&src)
+            .map_err(|errs| errs.into_iter().next().unwrap())
+            .and_then(|ast| self.run(ast));
+        self.file_name = previous;
+
+        res
+    }
+
+    /// Calls the Fang function `name` with `args`, converting its result to `T`.
+    ///
+    /// ```
+    /// let mut engine = fang::Engine::new();
+    /// engine.eval("fn area(w: int, h: int): int { return w * h; }").unwrap();
+    /// let area: u64 = engine.call_fn("area", (3u64, 4u64)).unwrap();
+    /// assert_eq!(area, 12);
+    /// ```
+    pub fn call_fn<T: FromFang, A: IntoArgs>(&mut self, name: &str, args: A) -> Result<T, FangErr> {
+        if self.scope.get_fn(name).is_none() {
+            return Err(FangErr::UndeclaredFunction {
+                span: Spans::empty(),
+                name: name.to_string(),
+                scope: self.scope.name.clone(),
+            });
+        }
+
+        // Arity and parameter types are checked by the call itself. `none` and functions
+        // aren't values a call can be given, they'd compile to nothing and a declaration.
+        let args = args
+            .into_args()?
+            .into_iter()
+            .map(|arg| match arg {
+                Value::None | Value::Function(_) => Err(FangErr::TypeMismatch {
+                    span: Spans::empty(),
+                    expected: "int, float, string, bool, object or native value".to_string(),
+                    found: arg.get_type(),
+                    scope: self.scope.name.clone(),
+                }),
+                arg => Ok(arg.into_node()),
+            })
+            .collect::<Result<Vec<Node>, FangErr>>()?;
+        let call = Node::Call {
+            name: name.to_string(),
+            args: Box::new(args),
+            span: Spans::empty(),
+        };
+        let value = self.run(vec![Node::Return {
+            value: Box::new(call),
+            span: Spans::empty(),
+        }])?;
+
+        T::from_fang(value)
+    }
+
+    /// Declares `name` in the global scope, or replaces its value if it's already declared.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.define(name, value.into_node());
+    }
+
+    /// Makes `f` callable from Fang as `name`, converting its arguments and result with
+    /// `FromFang` and `IntoFang`.
+    pub fn register_fn<Args, F: NativeFn<Args>>(&mut self, name: &str, f: F) {
+        self.define(name, native(name, f));
+    }
+
+    /// Makes the Rust type `T` available to scripts, with the fields and methods in `ty`. Its
+    /// constructor, if any, is called as `Name.new(...)`, and scripts can give it methods with
+    /// `impl Trait for Name`.
+    pub fn register_type<T: FangType>(&mut self, ty: NativeType<T>) {
+        self.scope.types.insert(
+            T::NAME.to_string(),
+            Type::Native {
+                name: T::NAME.to_string(),
+                class: Rc::new(ty.class),
+                implements: vec![],
+                implementations: vec![],
+            },
+        );
+
+        if let Some(constructor) = ty.constructor {
+            self.define(
+                T::NAME,
+                Node::Object {
+                    typed: "<Internal>".to_string(),
+                    fields: Box::new(vec![Node::Field {
+                        name: "new".to_string(),
+                        value: Box::new(constructor),
+                        span: Spans::empty(),
+                    }]),
+                    span: Spans::empty(),
+                },
+            );
+        }
+    }
+
+    fn define(&mut self, name: &str, node: Node) {
+        if self.scope.store.contains_key(name) {
+            self.scope.store.insert(name.to_string(), node);
+        } else {
+            self.scope
+                .declare(name.to_string(), node, &Spans::empty())
+                .unwrap();
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.scope.store.get(name).cloned().map(Value::from_node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::Capture;
+
+    #[test]
+    fn eval_returns_the_last_expression() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("let a = 2;").unwrap(), Value::None);
+        assert_eq!(engine.eval("a * 3").unwrap(), Value::Int(6));
+        assert!(matches!(
+            engine.eval("b;"),
+            Err(FangErr::UndeclaredVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn globals() {
+        let mut engine = Engine::new();
+        engine.set_global("name", Value::String("fang".to_string()));
+        engine.eval("let greeting = \"hi \" + name;").unwrap();
+        assert_eq!(
+            engine.get_global("greeting"),
+            Some(Value::String("hi fang".to_string()))
+        );
+
+        engine.set_global("name", Value::Int(1));
+        assert_eq!(engine.get_global("name"), Some(Value::Int(1)));
+        assert_eq!(engine.get_global("missing"), None);
+    }
+
+    #[test]
+    fn scripts_return_their_value() {
+        let mut engine = Engine::new();
+        let ast = engine.parse("let a = 1;\nreturn a + 1;").unwrap();
+        assert!(!engine.compile(ast.clone()).is_empty());
+        assert_eq!(engine.run(ast).unwrap(), Value::Int(2));
+        assert!(matches!(
+            engine.run_file("missing.fg"),
+            Err(FangErr::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn calls_fang_functions() {
+        let mut engine = Engine::new();
+        engine
+            .eval("fn greet(name: string): string { return \"hi \" + name; }")
+            .unwrap();
+
+        let greeting: String = engine.call_fn("greet", ("fang",)).unwrap();
+        assert_eq!(greeting, "hi fang");
+
+        let err = engine.call_fn::<String, _>("greet", (1u64,)).unwrap_err();
+        assert!(matches!(err.kind(), FangErr::TypeMismatch { .. }));
+        let err = engine.call_fn::<String, _>("greet", ()).unwrap_err();
+        assert!(matches!(err.kind(), FangErr::ArgumentLengthMismatch { .. }));
+        let err = engine.call_fn::<(), _>("missing", ()).unwrap_err();
+        assert!(matches!(err, FangErr::UndeclaredFunction { .. }));
+        let err = engine
+            .call_fn::<String, _>("greet", (Value::None,))
+            .unwrap_err();
+        assert!(matches!(err, FangErr::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn calls_without_a_result_give_none() {
+        let mut engine = Engine::new().with_output(Capture::new());
+        engine.register_fn("touch", || ());
+
+        engine
+            .eval("let x = print(\"a\");\nlet y = touch();")
+            .unwrap();
+        assert_eq!(engine.get_global("x"), Some(Value::None));
+        assert_eq!(engine.get_global("y"), Some(Value::None));
+
+        for src in ["1 + print(\"a\")", "touch() * 2", "\"s\" + touch()"] {
+            let err = engine.eval(src).unwrap_err();
+            assert!(
+                matches!(err.kind(), FangErr::OperationUnsupported { .. }),
+                "{src}"
+            );
+        }
+        let err = engine
+            .eval("let t = trait T { fn f(self): int; };")
+            .unwrap_err();
+        assert!(matches!(err.kind(), FangErr::MissingValue { .. }));
+    }
+
+    #[test]
+    fn runs_structs_traits_and_impls() {
+        let mut engine = Engine::new();
+        engine.run_file("basic.fg").unwrap();
+        assert_eq!(engine.eval("c.x").unwrap(), Value::Int(4));
+        assert_eq!(engine.eval("c.y").unwrap(), Value::Int(6));
+
+        let err = engine.eval("Vector { x: 1, y: \"2\" }").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::TypeMismatch { .. }));
+    }
+}
@@ -1,6 +1,18 @@
 use std::fmt::Display;
 
-use crate::ast::Spans;
+use crate::{ast::Spans, FILE_NAME};
+
+pub const EXIT_RUNTIME: i32 = 1;
+pub const EXIT_SYNTAX: i32 = 2;
+pub const EXIT_TYPE: i32 = 3;
+
+/// A call that was being executed when an error was raised.
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    pub name: String,
+    pub caller: String,
+    pub span: Spans,
+}
 
 #[derive(Debug)]
 pub enum FangErr {
@@ -34,11 +46,13 @@ pub enum FangErr {
     },
     AlreadyDeclaredVariable {
         span: Spans,
+        previous: Option<Spans>,
         name: String,
         scope: String,
     },
     AlreadyDeclaredFunction {
         span: Spans,
+        previous: Option<Spans>,
         name: String,
         scope: String,
     },
@@ -75,160 +89,507 @@ pub enum FangErr {
         found: String,
         scope: String,
     },
+    DivisionByZero {
+        span: Spans,
+        op: String,
+        lhs: String,
+        scope: String,
+    },
+    IntegerOverflow {
+        span: Spans,
+        op: String,
+        lhs: String,
+        rhs: String,
+        scope: String,
+    },
+    StackOverflow {
+        span: Spans,
+        depth: usize,
+        scope: String,
+    },
+    ExecutionBudgetExceeded {
+        span: Spans,
+        budget: u64,
+        scope: String,
+    },
+    HeapLimitExceeded {
+        span: Spans,
+        limit: usize,
+        used: usize,
+        scope: String,
+    },
+    SyntaxError {
+        span: Spans,
+        message: String,
+        hints: Vec<String>,
+    },
+    /// Raised by `exit` to unwind the script, only reported where it can't end the process.
+    Exit { span: Spans, code: i32 },
+    Io {
+        span: Spans,
+        path: String,
+        message: String,
+    },
+    /// Returned by a function registered from Rust.
+    Native { span: Spans, message: String },
+    PermissionDenied {
+        span: Spans,
+        name: String,
+        capability: String,
+    },
+    /// A format string given to `format` that is malformed or uses more arguments than given.
+    InvalidFormat { span: Spans, message: String },
+    /// A builtin was given a value it can't work with, like a string that isn't a number.
+    InvalidValue { span: Spans, message: String },
+    /// An operation found no value to work on, because the expression before it produced none.
+    MissingValue {
+        span: Spans,
+        op: String,
+        scope: String,
+    },
+    /// An error raised inside function calls, with the calls it unwound through, innermost first.
+    Traced {
+        err: Box<FangErr>,
+        calls: Vec<CallSite>,
+    },
 }
 
-impl Display for FangErr {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl FangErr {
+    /// Stable identifier for the kind of error, never reused once assigned.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FangErr::TypeMismatch { .. } => "F0001",
+            FangErr::OperationUnsupported { .. } => "F0002",
+            FangErr::UndeclaredVariable { .. } => "F0003",
+            FangErr::UndeclaredType { .. } => "F0004",
+            FangErr::UndeclaredFunction { .. } => "F0005",
+            FangErr::AlreadyDeclaredVariable { .. } => "F0006",
+            FangErr::AlreadyDeclaredFunction { .. } => "F0007",
+            FangErr::AlreadyDeclaredTrait { .. } => "F0008",
+            FangErr::AlreadyDeclaredStruct { .. } => "F0009",
+            FangErr::AlreadyImplementedTrait { .. } => "F0010",
+            FangErr::UnexpectedToken { .. } => "F0011",
+            FangErr::ArgumentLengthMismatch { .. } => "F0012",
+            FangErr::UnexpectedType { .. } => "F0013",
+            FangErr::DivisionByZero { .. } => "F0014",
+            FangErr::IntegerOverflow { .. } => "F0015",
+            FangErr::StackOverflow { .. } => "F0016",
+            FangErr::ExecutionBudgetExceeded { .. } => "F0017",
+            FangErr::HeapLimitExceeded { .. } => "F0018",
+            FangErr::SyntaxError { .. } => "F0019",
+            FangErr::Exit { .. } => "F0020",
+            FangErr::Io { .. } => "F0021",
+            FangErr::Native { .. } => "F0022",
+            FangErr::PermissionDenied { .. } => "F0023",
+            FangErr::InvalidFormat { .. } => "F0024",
+            FangErr::InvalidValue { .. } => "F0025",
+            FangErr::MissingValue { .. } => "F0026",
+            FangErr::Traced { err, .. } => err.code(),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            FangErr::TypeMismatch { .. } => "Type mismatch",
+            FangErr::OperationUnsupported { .. } => "Operation unsupported",
+            FangErr::UndeclaredVariable { .. } => "Undeclared variable",
+            FangErr::UndeclaredType { .. } => "Undeclared type",
+            FangErr::UndeclaredFunction { .. } => "Undeclared function",
+            FangErr::AlreadyDeclaredVariable { .. }
+            | FangErr::AlreadyDeclaredFunction { .. }
+            | FangErr::AlreadyDeclaredTrait { .. }
+            | FangErr::AlreadyDeclaredStruct { .. } => "Already declared",
+            FangErr::AlreadyImplementedTrait { .. } => "Already implemented",
+            FangErr::UnexpectedToken { .. } => "Unexpected token",
+            FangErr::ArgumentLengthMismatch { .. } => "Argument mismatch",
+            FangErr::UnexpectedType { .. } => "Unexpected type",
+            FangErr::DivisionByZero { .. } => "Division by zero",
+            FangErr::IntegerOverflow { .. } => "Integer overflow",
+            FangErr::StackOverflow { .. } => "Stack overflow",
+            FangErr::ExecutionBudgetExceeded { .. } => "Execution budget exceeded",
+            FangErr::HeapLimitExceeded { .. } => "Heap limit exceeded",
+            FangErr::SyntaxError { .. } => "Syntax error",
+            FangErr::Exit { .. } => "Exited",
+            FangErr::Io { .. } => "I/O error",
+            FangErr::Native { .. } => "Native function failed",
+            FangErr::PermissionDenied { .. } => "Permission denied",
+            FangErr::InvalidFormat { .. } => "Invalid format string",
+            FangErr::InvalidValue { .. } => "Invalid value",
+            FangErr::MissingValue { .. } => "Missing value",
+            FangErr::Traced { err, .. } => err.title(),
+        }
+    }
+
+    pub fn message(&self) -> String {
         match self {
             FangErr::TypeMismatch {
                 expected,
                 found,
                 scope,
-                span,
-            } => {
-                write!(
-                    f,
-                    "[Type mismatch]: Expected {}, found {} in scope {}\n{}",
-                    expected,
-                    found,
-                    scope,
-                    span.snippet()
-                )
-            }
+                ..
+            } => format!("Expected {expected}, found {found} in scope {scope}"),
             FangErr::OperationUnsupported {
                 op,
                 lhs,
                 rhs,
                 scope,
-                span,
-            } => {
-                write!(
-                    f,
-                    "[Operation unsupported]: Tried to {} {} and {} in scope {}\n{}",
-                    lhs,
-                    op,
-                    rhs,
-                    scope,
-                    span.snippet()
-                )
+                ..
+            } => format!("Tried to {op} {lhs} and {rhs} in scope {scope}"),
+            FangErr::UndeclaredVariable { name, scope, .. } => {
+                format!("Variable {name} not found in scope {scope}")
             }
-            FangErr::UndeclaredVariable { name, scope, span } => {
-                write!(
-                    f,
-                    "[Undeclared variable]: Variable {} not found in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
-            }
-            FangErr::UndeclaredFunction { name, scope, span } => {
-                write!(
-                    f,
-                    "[Undeclared function]: Function {} not found in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::UndeclaredFunction { name, scope, .. } => {
+                format!("Function {name} not found in scope {scope}")
             }
-            FangErr::UndeclaredType { name, scope, span } => {
-                write!(
-                    f,
-                    "[Undeclared type]: Type {} not found in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::UndeclaredType { name, scope, .. } => {
+                format!("Type {name} not found in scope {scope}")
             }
-            FangErr::AlreadyDeclaredVariable { name, scope, span } => {
-                write!(
-                    f,
-                    "[Already declared]: Variable {} already declared in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::AlreadyDeclaredVariable { name, scope, .. } => {
+                format!("Variable {name} already declared in scope {scope}")
             }
-            FangErr::AlreadyDeclaredFunction { name, scope, span } => {
-                write!(
-                    f,
-                    "[Already declared]: Function {} already declared in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::AlreadyDeclaredFunction { name, scope, .. } => {
+                format!("Function {name} already declared in scope {scope}")
             }
-            FangErr::AlreadyDeclaredTrait { name, scope, span } => {
-                write!(
-                    f,
-                    "[Already declared]: Trait {} already declared in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::AlreadyDeclaredTrait { name, scope, .. } => {
+                format!("Trait {name} already declared in scope {scope}")
             }
-            FangErr::AlreadyDeclaredStruct { name, scope, span } => {
-                write!(
-                    f,
-                    "[Already declared]: Struct {} already declared in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::AlreadyDeclaredStruct { name, scope, .. } => {
+                format!("Struct {name} already declared in scope {scope}")
             }
-            FangErr::AlreadyImplementedTrait { name, scope, span } => {
-                write!(
-                    f,
-                    "[Already implemented]: Trait {} already implemented in scope {}\n{}",
-                    name,
-                    scope,
-                    span.snippet()
-                )
+            FangErr::AlreadyImplementedTrait { name, scope, .. } => {
+                format!("Trait {name} already implemented in scope {scope}")
             }
             FangErr::UnexpectedToken {
                 expected,
                 found,
                 scope,
-                span,
-            } => {
-                write!(
-                    f,
-                    "[Unexpected token]: Expected {}, found {} in scope {}\n{}",
-                    expected,
-                    found,
-                    scope,
-                    span.snippet()
-                )
-            }
+                ..
+            } => format!("Expected {expected}, found {found} in scope {scope}"),
             FangErr::ArgumentLengthMismatch {
                 expected,
                 found,
                 scope,
-                span,
-            } => {
-                write!(
-                    f,
-                    "[Argument mismatch]: Expected {} arguments, found {} in scope {}\n{}",
-                    expected,
-                    found,
-                    scope,
-                    span.snippet()
-                )
-            }
+                ..
+            } => format!("Expected {expected} arguments, found {found} in scope {scope}"),
             FangErr::UnexpectedType {
                 expected,
                 found,
                 scope,
-                span,
-            } => {
-                write!(
-                    f,
-                    "[Unexpected type]: Expected {}, found {} in scope {}\n{}",
-                    expected,
-                    found,
-                    scope,
-                    span.snippet()
+                ..
+            } => format!("Expected {expected}, found {found} in scope {scope}"),
+            FangErr::DivisionByZero { op, lhs, scope, .. } => {
+                format!("Tried to {op} {lhs} by zero in scope {scope}")
+            }
+            FangErr::IntegerOverflow {
+                op,
+                lhs,
+                rhs,
+                scope,
+                ..
+            } => format!("Tried to {op} {lhs} and {rhs} in scope {scope}"),
+            FangErr::StackOverflow { depth, scope, .. } => {
+                format!("Call depth exceeded {depth} in scope {scope}")
+            }
+            FangErr::ExecutionBudgetExceeded { budget, scope, .. } => {
+                format!("Executed more than {budget} operations in scope {scope}")
+            }
+            FangErr::HeapLimitExceeded {
+                limit, used, scope, ..
+            } => format!("Using {used} bytes, limit is {limit} in scope {scope}"),
+            FangErr::SyntaxError { message, .. } => message.clone(),
+            FangErr::Exit { code, .. } => format!("Script exited with status {code}"),
+            FangErr::Io { path, message, .. } => format!("Unable to access {path}: {message}"),
+            FangErr::Native { message, .. } => message.clone(),
+            FangErr::PermissionDenied {
+                name, capability, ..
+            } => format!("{name} needs the {capability} capability, which is disabled"),
+            FangErr::InvalidFormat { message, .. } | FangErr::InvalidValue { message, .. } => {
+                message.clone()
+            }
+            FangErr::MissingValue { op, scope, .. } => {
+                format!("Nothing to {op} in scope {scope}")
+            }
+            FangErr::Traced { err, .. } => err.message(),
+        }
+    }
+
+    pub fn span(&self) -> &Spans {
+        match self {
+            FangErr::TypeMismatch { span, .. }
+            | FangErr::OperationUnsupported { span, .. }
+            | FangErr::UndeclaredVariable { span, .. }
+            | FangErr::UndeclaredType { span, .. }
+            | FangErr::UndeclaredFunction { span, .. }
+            | FangErr::AlreadyDeclaredVariable { span, .. }
+            | FangErr::AlreadyDeclaredFunction { span, .. }
+            | FangErr::AlreadyDeclaredTrait { span, .. }
+            | FangErr::AlreadyDeclaredStruct { span, .. }
+            | FangErr::AlreadyImplementedTrait { span, .. }
+            | FangErr::UnexpectedToken { span, .. }
+            | FangErr::ArgumentLengthMismatch { span, .. }
+            | FangErr::UnexpectedType { span, .. }
+            | FangErr::DivisionByZero { span, .. }
+            | FangErr::IntegerOverflow { span, .. }
+            | FangErr::StackOverflow { span, .. }
+            | FangErr::ExecutionBudgetExceeded { span, .. }
+            | FangErr::HeapLimitExceeded { span, .. }
+            | FangErr::SyntaxError { span, .. }
+            | FangErr::Exit { span, .. }
+            | FangErr::Io { span, .. }
+            | FangErr::Native { span, .. }
+            | FangErr::PermissionDenied { span, .. }
+            | FangErr::InvalidFormat { span, .. }
+            | FangErr::InvalidValue { span, .. }
+            | FangErr::MissingValue { span, .. } => span,
+            FangErr::Traced { err, .. } => err.span(),
+        }
+    }
+
+    /// Secondary locations worth pointing at, alongside their label.
+    pub fn related(&self) -> Vec<(&Spans, &'static str)> {
+        match self {
+            FangErr::AlreadyDeclaredVariable {
+                previous: Some(previous),
+                ..
+            }
+            | FangErr::AlreadyDeclaredFunction {
+                previous: Some(previous),
+                ..
+            } if !previous.is_empty() => vec![(previous, "previously declared here")],
+            FangErr::Traced { err, .. } => err.related(),
+            _ => vec![],
+        }
+    }
+
+    pub fn hints(&self) -> &[String] {
+        match self {
+            FangErr::SyntaxError { hints, .. } => hints,
+            FangErr::Traced { err, .. } => err.hints(),
+            _ => &[],
+        }
+    }
+
+    /// The error itself, without the calls it was raised in.
+    pub fn kind(&self) -> &FangErr {
+        match self {
+            FangErr::Traced { err, .. } => err,
+            err => err,
+        }
+    }
+
+    /// The calls the error unwound through, outermost first.
+    pub fn trace(&self) -> Vec<&CallSite> {
+        match self {
+            FangErr::Traced { calls, .. } => calls.iter().rev().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The status the process should exit with: the script's own for `exit`, 2 for syntax
+    /// errors, 3 for type errors and 1 for any other runtime error.
+    pub fn exit_code(&self) -> i32 {
+        match self.kind() {
+            FangErr::Exit { code, .. } => *code,
+            FangErr::SyntaxError { .. } => EXIT_SYNTAX,
+            FangErr::TypeMismatch { .. }
+            | FangErr::OperationUnsupported { .. }
+            | FangErr::UndeclaredType { .. }
+            | FangErr::ArgumentLengthMismatch { .. }
+            | FangErr::UnexpectedType { .. } => EXIT_TYPE,
+            _ => EXIT_RUNTIME,
+        }
+    }
+
+    /// Records that the error unwound through a call to `name` made by `caller` at `span`.
+    pub fn called_from(self, name: &str, caller: &str, span: &Spans) -> FangErr {
+        let call = CallSite {
+            name: name.to_string(),
+            caller: caller.to_string(),
+            span: span.clone(),
+        };
+
+        // Builtins don't know where they were called from, so their errors point at the call.
+        let mut err = self;
+        if let FangErr::Io { span: at, .. }
+        | FangErr::Native { span: at, .. }
+        | FangErr::PermissionDenied { span: at, .. }
+        | FangErr::InvalidFormat { span: at, .. }
+        | FangErr::InvalidValue { span: at, .. } = &mut err
+        {
+            if at.is_empty() {
+                *at = span.clone();
+            }
+        }
+
+        match err {
+            FangErr::Exit { .. } => err,
+            FangErr::Traced { err, mut calls } => {
+                calls.push(call);
+                FangErr::Traced { err, calls }
+            }
+            err => FangErr::Traced {
+                err: Box::new(err),
+                calls: vec![call],
+            },
+        }
+    }
+
+    /// Renders the error as a single line JSON object, for editors and CI.
+    pub fn to_json(&self) -> String {
+        let related = self
+            .related()
+            .iter()
+            .map(|(span, label)| {
+                format!(
+                    r#"{{"message":{},"span":{}}}"#,
+                    json_str(label),
+                    json_span(span)
+                )
+            })
+            .collect::<Vec<String>>();
+        let hints = self
+            .hints()
+            .iter()
+            .map(|h| json_str(h))
+            .collect::<Vec<String>>();
+        let trace = self
+            .trace()
+            .iter()
+            .map(|c| {
+                format!(
+                    r#"{{"name":{},"caller":{},"span":{}}}"#,
+                    json_str(&c.name),
+                    json_str(&c.caller),
+                    json_span(&c.span)
                 )
+            })
+            .collect::<Vec<String>>();
+
+        format!(
+            r#"{{"code":{},"severity":"error","title":{},"message":{},"file":{},"span":{},"related":[{}],"hints":[{}],"trace":[{}]}}"#,
+            json_str(self.code()),
+            json_str(self.title()),
+            json_str(&self.message()),
+            json_str(&FILE_NAME.lock().unwrap()),
+            json_span(self.span()),
+            related.join(","),
+            hints.join(","),
+            trace.join(",")
+        )
+    }
+}
+
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub(crate) fn json_span(span: &Spans) -> String {
+    if span.is_empty() {
+        return "null".to_string();
+    }
+
+    format!(
+        r#"{{"start":{{"line":{},"column":{}}},"end":{{"line":{},"column":{}}}}}"#,
+        span.line(),
+        span.col(),
+        span.end_line(),
+        span.end_col()
+    )
+}
+
+impl Display for FangErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{} {}]: {}", self.code(), self.title(), self.message())?;
+        // Errors raised outside of any source, like a missing file, have no location.
+        if !self.span().is_empty() {
+            write!(f, "\n{}", self.span().snippet())?;
+        }
+        for (span, label) in self.related() {
+            write!(f, "\n\n{}", span.label(label))?;
+        }
+        for hint in self.hints() {
+            write!(f, "\nhelp: {}", hint)?;
+        }
+
+        let trace = self.trace();
+        if !trace.is_empty() {
+            write!(f, "\n\nCall stack (most recent call last):")?;
+        }
+
+        // Deep recursion is collapsed the same way Python does, one line per run of repeats.
+        let mut i = 0;
+        while i < trace.len() {
+            let call = trace[i];
+            let repeats = trace[i + 1..]
+                .iter()
+                .take_while(|c| c.name == call.name && c.span == call.span)
+                .count();
+
+            write!(
+                f,
+                "\n  {} in {}, calling {}\n    {}",
+                call.span.location(),
+                call.caller,
+                call.name,
+                call.span.source().lines().next().unwrap_or("").trim()
+            )?;
+            if repeats > 0 {
+                write!(f, "\n  [Previous call repeated {} more times]", repeats)?;
             }
+
+            i += repeats + 1;
         }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_escapes_and_locates() {
+        let err = FangErr::SyntaxError {
+            span: Spans::new("let s = \"a\";", ((1, 9), (1, 12))),
+            message: "unexpected \"a\"\n".to_string(),
+            hints: vec!["delete `\"a\"`".to_string()],
+        };
+
+        let json = err.to_json();
+        assert!(
+            json.starts_with(r#"{"code":"F0019","severity":"error","#),
+            "{json}"
+        );
+        assert!(json.contains(r#""message":"unexpected \"a\"\n""#), "{json}");
+        assert!(
+            json.contains(r#""span":{"start":{"line":1,"column":9},"end":{"line":1,"column":12}}"#)
+        );
+        assert!(json.contains(r#""hints":["delete `\"a\"`"]"#), "{json}");
+    }
+
+    #[test]
+    fn json_without_location() {
+        let err = FangErr::StackOverflow {
+            span: Spans::empty(),
+            depth: 1,
+            scope: "f".to_string(),
+        };
+
+        assert!(err.to_json().contains(r#""span":null"#));
     }
 }
@@ -0,0 +1,313 @@
+use std::fmt::Display;
+
+/// Long-form documentation for an error code, as printed by `fang explain`.
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub example: &'static str,
+    pub fix: &'static str,
+}
+
+// Codes are stable: when an error kind is removed its entry stays, and new kinds are
+// appended with the next free code.
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "F0001",
+        title: "Type mismatch",
+        description: "A value of one type was used where another type is required, for \
+            example when passing an argument whose type doesn't match the parameter.",
+        example: "fn double(x: int): int { return x * 2; }\ndouble(\"two\");",
+        fix: "fn double(x: int): int { return x * 2; }\ndouble(2);",
+    },
+    Explanation {
+        code: "F0002",
+        title: "Operation unsupported",
+        description: "An arithmetic operator was applied to values it isn't defined for. \
+            Only `+` accepts strings, which concatenates them.",
+        example: "let a = 1 - \"one\";",
+        fix: "let a = 1 - 1;",
+    },
+    Explanation {
+        code: "F0003",
+        title: "Undeclared variable",
+        description: "A variable was read or assigned before being declared with `let`, or \
+            was declared in a scope that isn't visible from here.",
+        example: "let total = count + 1;",
+        fix: "let count = 0;\nlet total = count + 1;",
+    },
+    Explanation {
+        code: "F0004",
+        title: "Undeclared type",
+        description: "A struct or trait was referenced before being declared.",
+        example: "impl Show for Point { fn show(self): string { return \"p\"; } };",
+        fix: "struct Point { x: int };\ntrait Show { fn show(self): string; };\n\
+            impl Show for Point { fn show(self): string { return \"p\"; } };",
+    },
+    Explanation {
+        code: "F0005",
+        title: "Undeclared function",
+        description: "A function was called that hasn't been declared in this scope or any \
+            of its parents. Functions must be declared before the call runs.",
+        example: "let a = triple(3);",
+        fix: "fn triple(x: int): int { return x * 3; }\nlet a = triple(3);",
+    },
+    Explanation {
+        code: "F0006",
+        title: "Already declared",
+        description: "A variable was declared with `let` twice in the same scope. Use \
+            assignment to change the value of an existing variable.",
+        example: "let a = 1;\nlet a = 2;",
+        fix: "let a = 1;\na = 2;",
+    },
+    Explanation {
+        code: "F0007",
+        title: "Already declared",
+        description: "A function was declared twice in the same scope. Fang doesn't \
+            support overloading, so give the second function a different name.",
+        example: "fn f(): int { return 1; }\nfn f(): int { return 2; }",
+        fix: "fn f(): int { return 1; }\nfn g(): int { return 2; }",
+    },
+    Explanation {
+        code: "F0008",
+        title: "Already declared",
+        description: "A trait was declared twice in the same scope.",
+        example: "trait Show { fn show(self): string; };\ntrait Show { fn show(self): string; };",
+        fix: "trait Show { fn show(self): string; };",
+    },
+    Explanation {
+        code: "F0009",
+        title: "Already declared",
+        description: "A struct was declared twice in the same scope.",
+        example: "struct Point { x: int };\nstruct Point { x: int };",
+        fix: "struct Point { x: int };",
+    },
+    Explanation {
+        code: "F0010",
+        title: "Already implemented",
+        description: "A trait was implemented more than once for the same struct.",
+        example: "struct Point { x: int };\ntrait Show { fn show(self): string; };\n\
+            impl Show for Point { fn show(self): string { return \"p\"; } };\n\
+            impl Show for Point { fn show(self): string { return \"q\"; } };",
+        fix: "struct Point { x: int };\ntrait Show { fn show(self): string; };\n\
+            impl Show for Point { fn show(self): string { return \"p\"; } };",
+    },
+    Explanation {
+        code: "F0011",
+        title: "Unexpected token",
+        description: "A construct was found where the language expects something else, such \
+            as a trait implementation whose signature doesn't match the trait.",
+        example: "struct Point { x: int };\ntrait Show { fn show(self): string; };\n\
+            impl Show for Point { fn show(self) { return \"p\"; } };",
+        fix: "struct Point { x: int };\ntrait Show { fn show(self): string; };\n\
+            impl Show for Point { fn show(self): string { return \"p\"; } };",
+    },
+    Explanation {
+        code: "F0012",
+        title: "Argument mismatch",
+        description: "A function was called with a different number of arguments than it has \
+            parameters, or an object was given a different number of values than its \
+            struct has fields.",
+        example: "fn add(a: int, b: int): int { return a + b; }\nlet c = add(1);",
+        fix: "fn add(a: int, b: int): int { return a + b; }\nlet c = add(1, 2);",
+    },
+    Explanation {
+        code: "F0013",
+        title: "Unexpected type",
+        description: "A type name refers to the wrong kind of type, such as implementing a \
+            struct as if it were a trait.",
+        example: "struct Point { x: int };\nstruct Show { x: int };\n\
+            impl Show for Point { fn show(self): string { return \"p\"; } };",
+        fix: "struct Point { x: int };\ntrait Show { fn show(self): string; };\n\
+            impl Show for Point { fn show(self): string { return \"p\"; } };",
+    },
+    Explanation {
+        code: "F0014",
+        title: "Division by zero",
+        description: "An integer was divided by zero, or its remainder taken with zero. \
+            Float division follows IEEE 754 and produces `inf` or `NaN` instead.",
+        example: "let a = 10 / 0;",
+        fix: "let a = 10.0 / 0;",
+    },
+    Explanation {
+        code: "F0015",
+        title: "Integer overflow",
+        description: "Integers are unsigned 64-bit values, so arithmetic that would go \
+            below zero or above 18446744073709551615 is an error rather than wrapping.",
+        example: "let a = 0 - 1;",
+        fix: "let a = 0.0 - 1;",
+    },
+    Explanation {
+        code: "F0016",
+        title: "Stack overflow",
+        description: "Calls nested deeper than the limit set by `--max-depth`. Recursion \
+            through `return f(...)` reuses the current frame and doesn't count towards it.",
+        example: "fn down(n: int): int { return 1 + down(n + 1); }\ndown(0);",
+        fix: "fn down(n: int): int { return n + 1; }\ndown(0);",
+    },
+    Explanation {
+        code: "F0017",
+        title: "Execution budget exceeded",
+        description: "The program ran more operations than allowed by `--max-ops`, which \
+            usually means it loops forever.",
+        example: "fn spin(n: int): int { return spin(n); }\nspin(0);",
+        fix: "fn spin(n: int): int { return n; }\nspin(0);",
+    },
+    Explanation {
+        code: "F0018",
+        title: "Heap limit exceeded",
+        description: "The values held by the program grew beyond `--max-heap` bytes.",
+        example: "fn grow(s: string): string { return grow(s + s); }\ngrow(\"fang\");",
+        fix: "fn grow(s: string): string { return s + s; }\ngrow(\"fang\");",
+    },
+    Explanation {
+        code: "F0019",
+        title: "Syntax error",
+        description: "The source couldn't be parsed. The parser recovers where it can so \
+            that every error is reported, and suggests edits that would fix each one.",
+        example: "let a = 1\nlet b = 2;",
+        fix: "let a = 1;\nlet b = 2;",
+    },
+    Explanation {
+        code: "F0020",
+        title: "Exited",
+        description: "The script called `exit`. When running a script this ends the process \
+            with the given status, but under `fang test` it fails the test that called it.",
+        example: "exit(1);",
+        fix: "let failed = true;",
+    },
+    Explanation {
+        code: "F0021",
+        title: "I/O error",
+        description: "A file couldn't be read or written, by `fang run` or a script's `fs` \
+            calls, because it doesn't exist, its permissions don't allow it or it isn't \
+            valid UTF-8. Paths are relative to the directory Fang was started in.",
+        example: "fs.read_to_string(\"missing.txt\");",
+        fix: "let found = fs.exists(\"missing.txt\");",
+    },
+    Explanation {
+        code: "F0022",
+        title: "Native function failed",
+        description: "A function provided by the program embedding Fang, rather than written \
+            in Fang, returned an error. The message comes from that function.",
+        example: "// engine.register_fn(\"half\", |x: i64| if x % 2 == 0 { Ok(x / 2) } else { Err(\"odd\") });\nhalf(3);",
+        fix: "half(4);",
+    },
+    Explanation {
+        code: "F0023",
+        title: "Permission denied",
+        description: "A builtin was called that needs a capability, like file or environment \
+            access, which the engine running the script has disabled. Sandboxed engines, \
+            such as `fang run --sandbox`, only allow `console`.",
+        example: "// fang run --sandbox\nfs.exists(\"notes.txt\");",
+        fix: "// fang run --sandbox\nconsole.log(\"hi\");",
+    },
+    Explanation {
+        code: "F0024",
+        title: "Invalid format string",
+        description: "The format string given to `format` couldn't be used: a brace was left \
+            unclosed or unescaped, a format spec wasn't understood, or a placeholder refers \
+            to an argument that wasn't given. Literal braces are written `{{` and `}}`, and \
+            specs look like `{:>8}` or `{:.2}`, with widths and precisions of at most 65535.",
+        example: "format(\"{} and {}\", 1);",
+        fix: "format(\"{} and {}\", 1, 2);",
+    },
+    Explanation {
+        code: "F0025",
+        title: "Invalid value",
+        description: "A builtin was given a value of the right type that it still couldn't \
+            work with, such as a string passed to `parse_int` that isn't a number.",
+        example: "let count = \"twelve\";\ncount.parse_int();",
+        fix: "let count = \"12\";\ncount.parse_int();",
+    },
+    Explanation {
+        code: "F0026",
+        title: "Missing value",
+        description: "A value was needed, but the expression that should have given it \
+            produced none, such as a declaration used where an expression is expected.",
+        example: "let shape = trait Shape { fn area(self): int; };",
+        fix: "trait Shape { fn area(self): int; };",
+    },
+];
+
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|e| e.code.eq_ignore_ascii_case(code))
+}
+
+fn indent(src: &str) -> String {
+    src.lines()
+        .map(|l| format!("    {l}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}\n\n{}\n\nErroneous code example:\n\n{}\n\nFixed:\n\n{}\n",
+            self.code,
+            self.title,
+            self.description,
+            indent(self.example),
+            indent(self.fix)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{console::Capture, engine::Engine, sandbox::Profile};
+
+    // Examples starting with `// fang run --sandbox` run sandboxed, and the `half` that
+    // F0022's example mentions is registered for all of them. Test threads have a small
+    // native stack, so the call depth is kept well below the default.
+    fn run(src: &str) -> Result<(), String> {
+        let profile = match src.starts_with("// fang run --sandbox") {
+            true => Profile::untrusted(),
+            false => Profile::trusted(),
+        };
+        let mut engine = Engine::new()
+            .with_profile(
+                profile
+                    .max_depth(32)
+                    .max_ops(Some(100_000))
+                    .max_heap(Some(1024 * 1024)),
+            )
+            .with_output(Capture::new());
+        engine.register_fn("half", |x: i64| match x % 2 {
+            0 => Ok(x / 2),
+            _ => Err("odd".to_string()),
+        });
+
+        let ast = engine
+            .parse(src)
+            .map_err(|errs| errs[0].code().to_string())?;
+        engine.run(ast).map_err(|e| e.code().to_string())?;
+        Ok(())
+    }
+
+    #[test]
+    fn codes_are_sequential() {
+        for (i, e) in EXPLANATIONS.iter().enumerate() {
+            assert_eq!(e.code, format!("F{:04}", i + 1));
+        }
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(explain("f0003").map(|e| e.code), Some("F0003"));
+        assert!(explain("F9999").is_none());
+    }
+
+    #[test]
+    fn examples_raise_their_code() {
+        for e in EXPLANATIONS {
+            assert_eq!(run(e.example), Err(e.code.to_string()), "{}", e.code);
+            assert_eq!(run(e.fix), Ok(()), "{}", e.code);
+        }
+    }
+}
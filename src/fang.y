@@ -1,11 +1,35 @@
-%start StatementList
+%start StatementOrReturnList
+%epp FLOAT "float"
+%epp INTEGER "integer"
+%epp STRING "string"
+%epp FSTRING "f-string"
+%epp RSTRING "raw string"
+%epp MSTRING "multi-line string"
+%epp BOOLEAN "boolean"
+%epp IDENTIFIER "identifier"
+%epp ADD "+"
+%epp SUB "-"
+%epp MUL "*"
+%epp DIV "/"
+%epp MOD "%"
+%epp LPAREN "("
+%epp RPAREN ")"
+%epp LBRACE "{"
+%epp RBRACE "}"
+%epp COLON ":"
+%epp ',' ","
+%epp ';' ";"
+%epp ASSIGNMENT "="
+%epp DECLARATION "let"
+%epp FUNCTION "fn"
+%epp STRUCT "struct"
+%epp TRAIT "trait"
+%epp RETURN "return"
+%epp IMPL "impl"
+%epp FOR "for"
+%epp SELF "self"
 %%
 
-StatementList -> FRes<Vec<Node>>:
-    StatementList Statement { append($1.map_err(|_| ())?, $2.map_err(|_| ())?) }
-    | { Ok(vec![]) }
-    ;
-
 Statement -> FRes<Node>:
     ';' { Ok(Node::Empty) }
     | Expression ';' { $1 }
@@ -13,7 +37,7 @@ Statement -> FRes<Node>:
     ;
 
 StatementOrReturnList -> FRes<Vec<Node>>:
-    StatementOrReturnList StatementOrReturn { append($1.map_err(|_| ())?, $2.map_err(|_| ())?) }
+    StatementOrReturnList StatementOrReturn { append($1?, $2?) }
     | { Ok(vec![]) }
     ;
 
@@ -25,34 +49,34 @@ StatementOrReturn -> FRes<Node>:
 Expression -> FRes<Node>:
     Addition { $1 }
     | 'DECLARATION' TypedVariable 'ASSIGNMENT' Expression {
-        match $2.map_err(|_| ())? {
+        match $2? {
             Node::TypedVariable { var_type, name, .. } => {
                 Ok(Node::Declaration { name, var_type: Some(var_type), rhs: Some(Box::new($4?)), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
             },
-            _ => Err(())
+            target => Err(invalid_target(&target))
         }
     }
     | 'DECLARATION' PrimaryExpression 'ASSIGNMENT' Expression {
-        match $2.map_err(|_| ())? {
+        match $2? {
             Node::Identifier { val, .. } => {
                 Ok(Node::Declaration { name: val, var_type: None, rhs: Some(Box::new($4?)), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
             },
-            _ => Err(())
+            target => Err(invalid_target(&target))
         }
     }
     | PrimaryExpression 'ASSIGNMENT' Expression {
-        match $1.map_err(|_| ())? {
+        match $1? {
             Node::Identifier { val, .. } => {
                 Ok(Node::Assignment { name: val, rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
             },
-            _ => Err(())
+            target => Err(invalid_target(&target))
         }
     }
     ;
 
 ExpressionList -> FRes<Vec<Node>>:
-    ExpressionList ',' Expression { append($1.map_err(|_| ())?, $3.map_err(|_| ())?) }
-    | Expression { Ok(vec![$1.map_err(|_| ())?]) }
+    ExpressionList ',' Expression { append($1?, $3?) }
+    | Expression { Ok(vec![$1?]) }
     ;
 
 Addition -> FRes<Node>:
@@ -65,34 +89,35 @@ Subtraction -> FRes<Node>:
     | Subtraction 'SUB' Multiplication { Ok(Node::Subtract { lhs: Box::new($1?), rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
+// `*`, `/` and `%` share a level and associate to the left, so `7 / 2 % 2` is `(7 / 2) % 2`.
 Multiplication -> FRes<Node>:
-    Division { $1 }
-    | Multiplication 'MUL' Division { Ok(Node::Multiply { lhs: Box::new($1?), rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
-    ;
-
-Division -> FRes<Node>:
     PrimaryExpression { $1 }
-    | Division 'DIV' PrimaryExpression { Ok(Node::Divide { lhs: Box::new($1?), rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    | Multiplication 'MUL' PrimaryExpression { Ok(Node::Multiply { lhs: Box::new($1?), rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    | Multiplication 'DIV' PrimaryExpression { Ok(Node::Divide { lhs: Box::new($1?), rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    | Multiplication 'MOD' PrimaryExpression { Ok(Node::Modulo { lhs: Box::new($1?), rhs: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 TypedVariable -> FRes<Node>:
-    'IDENTIFIER' 'COLON' 'IDENTIFIER' { Ok(type_var($lexer.span_str(($1.map_err(|_| ())?).span()), $lexer.span_str(($3.map_err(|_| ())?).span()), Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))))? }
-    | 'IDENTIFIER' 'COLON' 'SELF' { Ok(type_var($lexer.span_str(($1.map_err(|_| ())?).span()), "self", Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))))? }
+    'IDENTIFIER' 'COLON' 'IDENTIFIER' { Ok(type_var(&text($lexer, $1)?, &text($lexer, $3)?, Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))))? }
+    | 'IDENTIFIER' 'COLON' 'SELF' { Ok(type_var(&text($lexer, $1)?, "self", Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))))? }
     | 'SELF' { Ok(Node::TypedVariable { var_type: "self".to_string(), name: "self".to_string(), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 TypedVariableList -> FRes<Vec<Node>>:
-    TypedVariableList ',' TypedVariable { append($1.map_err(|_| ())?, $3.map_err(|_| ())?) }
-    | TypedVariable { Ok(vec![$1.map_err(|_| ())?]) }
+    TypedVariableList ',' TypedVariable { append($1?, $3?) }
+    | TypedVariable { Ok(vec![$1?]) }
     ;
 
 PrimaryExpression -> FRes<Node>:
-    'IDENTIFIER' { Ok(Node::Identifier { val: $lexer.span_str(($1.map_err(|_| ())?).span()).to_string(), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'IDENTIFIER' { Ok(Node::Identifier { val: text($lexer, $1)?, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     | 'LPAREN' Expression 'RPAREN' { $2 }
-    | 'INTEGER' { parse_int($lexer.span_str(($1.map_err(|_| ())?).span()), Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
-    | 'FLOAT' { parse_float($lexer.span_str(($1.map_err(|_| ())?).span()), Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
-    | 'BOOLEAN' { parse_bool($lexer.span_str(($1.map_err(|_| ())?).span()), Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
-    | 'STRING' { parse_string($lexer.span_str(($1.map_err(|_| ())?).span()), Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
+    | 'INTEGER' { parse_int(&text($lexer, $1)?, Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
+    | 'FLOAT' { parse_float(&text($lexer, $1)?, Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
+    | 'BOOLEAN' { parse_bool(&text($lexer, $1)?, Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
+    | 'STRING' { parse_string(unquote(&text($lexer, $1)?, "\"", "\""), Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
+    | 'RSTRING' { Ok(Node::String { val: unquote(&text($lexer, $1)?, "r\"", "\"").to_string(), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    | 'MSTRING' { parse_multiline(&text($lexer, $1)?, Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
+    | 'FSTRING' { fstring(&text($lexer, $1)?, $lexer.line_col($span).0, Spans::new($lexer.span_lines_str($span), $lexer.line_col($span))) }
     | Object { $1 }
     | Struct { $1 }
     | Trait { $1 }
@@ -102,55 +127,55 @@ PrimaryExpression -> FRes<Node>:
 
 Function -> FRes<Node>:
     'FUNCTION' 'IDENTIFIER' 'LPAREN' TypedVariableList 'RPAREN' 'COLON' 'IDENTIFIER' 'LBRACE' StatementOrReturnList 'RBRACE' {
-        Ok(Node::Function { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new($4.map_err(|_| ())?), body: Box::new($9.map_err(|_| ())?), return_type: Some($lexer.span_str(($7.map_err(|_| ())?).span()).to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::Function { name: text($lexer, $2)?, args: Box::new($4?), body: Box::new($9?), return_type: Some(text($lexer, $7)?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' TypedVariableList 'RPAREN' 'COLON' 'SELF' 'LBRACE' StatementOrReturnList 'RBRACE' {
-        Ok(Node::Function { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new($4.map_err(|_| ())?), body: Box::new($9.map_err(|_| ())?), return_type: Some("self".to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::Function { name: text($lexer, $2)?, args: Box::new($4?), body: Box::new($9?), return_type: Some("self".to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' TypedVariableList 'RPAREN' 'LBRACE' StatementOrReturnList 'RBRACE' {
-        Ok(Node::Function { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new($4.map_err(|_| ())?), body: Box::new($7.map_err(|_| ())?), return_type: None, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::Function { name: text($lexer, $2)?, args: Box::new($4?), body: Box::new($7?), return_type: None, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' 'RPAREN' 'COLON' 'SELF' 'LBRACE' StatementOrReturnList 'RBRACE' {
-        Ok(Node::Function { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new(Vec::new()), body: Box::new($8.map_err(|_| ())?), return_type: Some("self".to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::Function { name: text($lexer, $2)?, args: Box::new(Vec::new()), body: Box::new($8?), return_type: Some("self".to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' 'RPAREN' 'COLON' 'IDENTIFIER' 'LBRACE' StatementOrReturnList 'RBRACE' {
-        Ok(Node::Function { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new(Vec::new()), body: Box::new($8.map_err(|_| ())?), return_type: Some($lexer.span_str(($7.map_err(|_| ())?).span()).to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::Function { name: text($lexer, $2)?, args: Box::new(Vec::new()), body: Box::new($8?), return_type: Some(text($lexer, $7)?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' 'RPAREN' 'LBRACE' StatementOrReturnList 'RBRACE' {
-        Ok(Node::Function { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new(Vec::new()), body: Box::new($6.map_err(|_| ())?), return_type: None, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::Function { name: text($lexer, $2)?, args: Box::new(Vec::new()), body: Box::new($6?), return_type: None, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     ;
 
 FunctionCall -> FRes<Node>:
-    'IDENTIFIER' 'LPAREN' ExpressionList 'RPAREN' { Ok(Node::Call { name: $lexer.span_str(($1.map_err(|_| ())?).span()).to_string(), args: Box::new($3.map_err(|_| ())?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
-    | 'IDENTIFIER' 'LPAREN' 'RPAREN' { Ok(Node::Call { name: $lexer.span_str(($1.map_err(|_| ())?).span()).to_string(), args: Box::new(Vec::new()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'IDENTIFIER' 'LPAREN' ExpressionList 'RPAREN' { Ok(Node::Call { name: text($lexer, $1)?, args: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    | 'IDENTIFIER' 'LPAREN' 'RPAREN' { Ok(Node::Call { name: text($lexer, $1)?, args: Box::new(Vec::new()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 Struct -> FRes<Node>:
-    'STRUCT' 'IDENTIFIER' 'LBRACE' TypedVariableList 'RBRACE' { Ok(Node::Struct { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), fields: Box::new($4.map_err(|_| ())?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'STRUCT' 'IDENTIFIER' 'LBRACE' TypedVariableList 'RBRACE' { Ok(Node::Struct { name: text($lexer, $2)?, fields: Box::new($4?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 Object -> FRes<Node>:
-    'IDENTIFIER' 'LBRACE' 'RBRACE' { Ok(Node::Object { typed: $lexer.span_str(($1.map_err(|_| ())?).span()).to_string(), fields: Box::new(vec![]), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
-    | 'IDENTIFIER' 'LBRACE' ObjectFields 'RBRACE' { Ok(Node::Object { typed:$lexer.span_str(($1.map_err(|_| ())?).span()).to_string(), fields: Box::new($3.map_err(|_| ())?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'IDENTIFIER' 'LBRACE' 'RBRACE' { Ok(Node::Object { typed: text($lexer, $1)?, fields: Box::new(vec![]), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    | 'IDENTIFIER' 'LBRACE' ObjectFields 'RBRACE' { Ok(Node::Object { typed:text($lexer, $1)?, fields: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 ObjectFields -> FRes<Vec<Node>>:
-    ObjectFields ',' ObjectField { append($1.map_err(|_| ())?, $3.map_err(|_| ())?)}
-    | ObjectField { Ok(vec![$1.map_err(|_| ())?]) }
+    ObjectFields ',' ObjectField { append($1?, $3?)}
+    | ObjectField { Ok(vec![$1?]) }
     ;
 
 ObjectField -> FRes<Node>:
-    'IDENTIFIER' 'COLON' Expression { Ok(Node::Field { name: $lexer.span_str(($1.map_err(|_| ())?).span()).to_string(), value: Box::new($3.map_err(|_| ())?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'IDENTIFIER' 'COLON' Expression { Ok(Node::Field { name: text($lexer, $1)?, value: Box::new($3?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 Trait -> FRes<Node>:
-    'TRAIT' 'IDENTIFIER' 'LBRACE' TraitFields 'RBRACE' { Ok(Node::Trait { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), fields: Box::new($4.map_err(|_| ())?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'TRAIT' 'IDENTIFIER' 'LBRACE' TraitFields 'RBRACE' { Ok(Node::Trait { name: text($lexer, $2)?, fields: Box::new($4?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 TraitFields -> FRes<Vec<Node>>:
-    TraitFields TraitField { append($1.map_err(|_| ())?, $2.map_err(|_| ())?)}
-    | TraitField { Ok(vec![$1.map_err(|_| ())?]) }
+    TraitFields TraitField { append($1?, $2?)}
+    | TraitField { Ok(vec![$1?]) }
     ;
 
 TraitField -> FRes<Node>:
@@ -160,23 +185,23 @@ TraitField -> FRes<Node>:
 
 FunctionOutline -> FRes<Node>:
     'FUNCTION' 'IDENTIFIER' 'LPAREN' TypedVariableList 'RPAREN' 'COLON' 'IDENTIFIER' ';' {
-        Ok(Node::FunctionOutline { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new($4.map_err(|_| ())?), return_type: Some($lexer.span_str(($7.map_err(|_| ())?).span()).to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::FunctionOutline { name: text($lexer, $2)?, args: Box::new($4?), return_type: Some(text($lexer, $7)?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' TypedVariableList 'RPAREN' 'COLON' 'SELF' ';' {
-        Ok(Node::FunctionOutline { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new($4.map_err(|_| ())?), return_type: Some("self".to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::FunctionOutline { name: text($lexer, $2)?, args: Box::new($4?), return_type: Some("self".to_string()), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     | 'FUNCTION' 'IDENTIFIER' 'LPAREN' 'RPAREN' ';' {
-        Ok(Node::FunctionOutline { name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), args: Box::new(Vec::new()), return_type: None, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
+        Ok(Node::FunctionOutline { name: text($lexer, $2)?, args: Box::new(Vec::new()), return_type: None, span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) })
     }
     ;
 
 TraitImpl -> FRes<Node>:
-    'IMPL' 'IDENTIFIER' 'FOR' 'IDENTIFIER' 'LBRACE' TraitImplFields 'RBRACE' { Ok(Node::TraitImpl { trait_name: $lexer.span_str(($2.map_err(|_| ())?).span()).to_string(), type_name: $lexer.span_str(($4.map_err(|_| ())?).span()).to_string(), fields: Box::new($6.map_err(|_| ())?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
+    'IMPL' 'IDENTIFIER' 'FOR' 'IDENTIFIER' 'LBRACE' TraitImplFields 'RBRACE' { Ok(Node::TraitImpl { trait_name: text($lexer, $2)?, type_name: text($lexer, $4)?, fields: Box::new($6?), span: Spans::new($lexer.span_lines_str($span), $lexer.line_col($span)) }) }
     ;
 
 TraitImplFields -> FRes<Vec<Node>>:
-    TraitImplFields TraitImplField { append($1.map_err(|_| ())?, $2.map_err(|_| ())?)}
-    | TraitImplField { Ok(vec![$1.map_err(|_| ())?]) }
+    TraitImplFields TraitImplField { append($1?, $2?)}
+    | TraitImplField { Ok(vec![$1?]) }
     ;
 
 TraitImplField -> FRes<Node>:
@@ -185,48 +210,71 @@ TraitImplField -> FRes<Node>:
 
 %%
 
-use crate::ast::*;
-type FRes<T> = Result<T, ()>;
+use lrlex::{DefaultLexeme, DefaultLexerTypes};
+use lrpar::NonStreamingLexer;
+
+use crate::{ast::*, errs::FangErr, format::fstring, strings::unescape};
+type FRes<T> = Result<T, FangErr>;
+
+fn syntax_error(message: String, span: Spans) -> FangErr {
+    FangErr::SyntaxError { span, message, hints: vec![] }
+}
+
+// Lexemes inserted during error recovery come through as `Err`, lrpar has already reported them.
+fn text(lexer: &dyn NonStreamingLexer<DefaultLexerTypes>, lexeme: Result<DefaultLexeme, DefaultLexeme>) -> FRes<String> {
+    match lexeme {
+        Ok(l) => Ok(lexer.span_str(l.span()).to_string()),
+        Err(l) => Err(syntax_error("missing token".to_string(), Spans::new(lexer.span_lines_str(l.span()), lexer.line_col(l.span())))),
+    }
+}
+
+fn invalid_target(node: &Node) -> FangErr {
+    syntax_error(format!("cannot assign to {}", node.get_type()), node.span())
+}
 
 fn parse_int(s: &str, sp: Spans) -> FRes<Node> {
    match s.parse::<u64>() {
     Ok(v) => Ok(Node::Integer { val: v, span: sp} ),
-    Err(_) => {
-        eprintln!("{} cannot be represented as an integer.", s);
-        Err(())
-    }
+    Err(_) => Err(syntax_error(format!("{} cannot be represented as an integer", s), sp)),
    }
 }
 
 fn parse_float(s: &str, sp: Spans) -> FRes<Node> {
    match s.parse::<f64>() {
     Ok(v) => Ok(Node::Float { val: v, span: sp} ),
-    Err(_) => {
-        eprintln!("{} cannot be represented as a float.", s);
-        Err(())
-    }
+    Err(_) => Err(syntax_error(format!("{} cannot be represented as a float", s), sp)),
    }
 }
 
 fn parse_bool(s: &str, sp: Spans) -> FRes<Node> {
    match s.parse::<bool>() {
     Ok(v) => Ok(Node::Boolean { val: v, span: sp } ),
-    Err(_) => {
-        eprintln!("{} cannot be represented as a bool.", s);
-        Err(())
-    }
+    Err(_) => Err(syntax_error(format!("{} cannot be represented as a bool", s), sp)),
    }
 }
 
+fn unquote<'a>(s: &'a str, open: &str, close: &str) -> &'a str {
+    &s[open.len()..s.len() - close.len()]
+}
+
 fn parse_string(s: &str, sp: Spans) -> FRes<Node> {
-    Ok(Node::String {val: s.to_string().split_at(1).1.split_at(s.len() - 2).0.to_string() , span: sp})
+    match unescape(s) {
+        Ok(val) => Ok(Node::String { val, span: sp }),
+        Err(message) => Err(syntax_error(message, sp)),
+    }
+}
+
+// A newline straight after the opening quotes isn't part of the string.
+fn parse_multiline(s: &str, sp: Spans) -> FRes<Node> {
+    let body = unquote(s, "\"\"\"", "\"\"\"");
+    parse_string(body.strip_prefix('\n').unwrap_or(body), sp)
 }
 
 fn type_var(name: &str, var_type: &str, sp: Spans) -> FRes<Node> {
    Ok(Node::TypedVariable { var_type: var_type.to_string(), name: name.to_string(), span: sp })
 }
 
-fn append(mut lhs: Vec<Node>, rhs: Node ) -> Result<Vec<Node>, ()>{
+fn append(mut lhs: Vec<Node>, rhs: Node ) -> FRes<Vec<Node>> {
     lhs.push(rhs);
     Ok(lhs)
 }
\ No newline at end of file
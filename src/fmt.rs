@@ -0,0 +1,255 @@
+use lrpar::{Lexeme, Lexer, NonStreamingLexer};
+
+use crate::{errs::FangErr, fang_l, parser::parse};
+
+const INDENT: &str = "    ";
+
+// Braces opened by these statements hold more statements (or fields) and go over several
+// lines, any other brace is an object literal and stays on one line.
+const BLOCK_STATEMENTS: &[&str] = &["fn", "struct", "trait", "impl"];
+
+struct Formatter {
+    out: String,
+    indent: usize,
+    blocks: Vec<bool>,
+    parens: usize,
+    statement: Option<String>,
+    prev: Option<String>,
+    line_start: bool,
+    closed_block: bool,
+}
+
+/// Reformats `src` with the standard layout, keeping its comments.
+pub fn format(src: &str) -> Result<String, Vec<FangErr>> {
+    parse(src)?;
+
+    let def = fang_l::lexerdef();
+    let lexer = def.lexer(src);
+    let mut f = Formatter {
+        out: String::new(),
+        indent: 0,
+        blocks: vec![],
+        parens: 0,
+        statement: None,
+        prev: None,
+        line_start: true,
+        closed_block: false,
+    };
+
+    let mut end = 0;
+    for lexeme in lexer.iter().filter_map(Result::ok) {
+        let span = lexeme.span();
+        f.gap(&src[end..span.start()]);
+        f.token(lexer.span_str(span));
+        end = span.end();
+    }
+    f.gap(&src[end..]);
+
+    let mut out = f.out.trim_end().to_string();
+    out.push('\n');
+    Ok(out)
+}
+
+fn is_operator(tok: &str) -> bool {
+    matches!(
+        tok,
+        "+" | "-" | "*" | "/" | "%" | "=" | "==" | "**" | "<" | ">"
+    )
+}
+
+impl Formatter {
+    fn newline(&mut self) {
+        if !self.line_start {
+            self.out.push('\n');
+            self.line_start = true;
+        }
+    }
+
+    fn write(&mut self, s: &str) {
+        if self.line_start {
+            self.out.push_str(&INDENT.repeat(self.indent));
+            self.line_start = false;
+        }
+        self.out.push_str(s);
+    }
+
+    /// Whitespace and comments between two tokens.
+    fn gap(&mut self, gap: &str) {
+        let mut rest = gap;
+        while let Some(start) = rest.find("//") {
+            let before = &rest[..start];
+            let comment = rest[start..].lines().next().unwrap_or("").trim_end();
+
+            if before.contains('\n') || self.out.is_empty() {
+                self.close_pending();
+                self.newline();
+                self.blank_line(before);
+            } else {
+                // A trailing comment stays on the line of the token before it.
+                if self.line_start && self.out.ends_with('\n') {
+                    self.out.pop();
+                    self.line_start = false;
+                }
+                self.out.push(' ');
+            }
+            self.write(comment);
+            self.newline();
+
+            rest = &rest[start + comment.len()..];
+        }
+
+        if !self.out.is_empty() && self.line_start {
+            self.blank_line(rest);
+        }
+    }
+
+    // A single blank line is kept wherever the source had one or more, except straight after
+    // an opening brace.
+    fn blank_line(&mut self, gap: &str) {
+        if gap.matches('\n').count() > 1 && !self.out.ends_with("{\n") && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    // `}` of a block is followed by a newline unless a `;` or `,` belongs to it.
+    fn close_pending(&mut self) {
+        if self.closed_block {
+            self.closed_block = false;
+            self.newline();
+        }
+    }
+
+    fn space_before(&self, tok: &str) -> bool {
+        let Some(prev) = self.prev.as_deref() else {
+            return false;
+        };
+
+        match (prev, tok) {
+            _ if self.line_start => false,
+            (_, "," | ";" | ")" | ":") => false,
+            ("(", _) => false,
+            ("{", "}") => false,
+            (p, "(") => is_operator(p) || matches!(p, "," | ":" | "return"),
+            _ => true,
+        }
+    }
+
+    fn token(&mut self, tok: &str) {
+        if self.closed_block && !matches!(tok, ";" | ",") {
+            self.closed_block = false;
+            self.newline();
+        }
+
+        if self.statement.is_none() {
+            self.statement = Some(tok.to_string());
+        }
+
+        match tok {
+            "{" => {
+                let block = self
+                    .statement
+                    .as_deref()
+                    .is_some_and(|s| BLOCK_STATEMENTS.contains(&s));
+                if self.space_before(tok) {
+                    self.write(" ");
+                }
+                self.write("{");
+                self.blocks.push(block);
+                if block {
+                    self.indent += 1;
+                    self.newline();
+                    self.statement = None;
+                }
+            }
+            "}" => match self.blocks.pop() {
+                Some(true) => {
+                    self.indent = self.indent.saturating_sub(1);
+                    self.newline();
+                    self.write("}");
+                    self.closed_block = true;
+                    self.statement = None;
+                }
+                _ => {
+                    if self.space_before(tok) {
+                        self.write(" ");
+                    }
+                    self.write("}");
+                }
+            },
+            ";" => {
+                self.write(";");
+                self.newline();
+                self.statement = None;
+            }
+            "," if self.parens == 0 && self.blocks.last() == Some(&true) => {
+                self.write(",");
+                self.newline();
+            }
+            _ => {
+                if self.space_before(tok) {
+                    self.write(" ");
+                }
+                self.write(tok);
+                match tok {
+                    "(" => self.parens += 1,
+                    ")" => self.parens = self.parens.saturating_sub(1),
+                    _ => (),
+                }
+            }
+        }
+
+        self.prev = Some(tok.to_string());
+        if self.line_start {
+            self.prev = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(src: &str) -> String {
+        format(src).unwrap()
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            fmt("let a=1+2*3;let b   =  a;"),
+            "let a = 1 + 2 * 3;\nlet b = a;\n"
+        );
+        assert_eq!(fmt("console.log( f(1,2) );"), "console.log(f(1, 2));\n");
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            fmt("fn f(x:int):int{let y=x;return y;}"),
+            "fn f(x: int): int {\n    let y = x;\n    return y;\n}\n"
+        );
+        assert_eq!(
+            fmt("struct V{x:int,y:int};let v=V{x:1,y:2};"),
+            "struct V {\n    x: int,\n    y: int\n};\nlet v = V { x: 1, y: 2 };\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_lines() {
+        assert_eq!(
+            fmt("// header\nlet a = 1; // one\n\n\n\nlet b = 2;\n"),
+            "// header\nlet a = 1; // one\n\nlet b = 2;\n"
+        );
+    }
+
+    #[test]
+    fn idempotent() {
+        let src = include_str!("../basic.fg");
+        assert_eq!(fmt(&fmt(src)), fmt(src));
+    }
+
+    #[test]
+    fn refuses_invalid_source() {
+        assert!(format("let a = ;").is_err());
+    }
+}
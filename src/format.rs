@@ -0,0 +1,425 @@
+use crate::{
+    ast::{Node, Spans},
+    errs::FangErr,
+    parser::parse,
+    strings::{escape_len, unescape},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// How a value is laid out in a placeholder: `[[fill]align][0][width][.precision]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            fill: ' ',
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+        }
+    }
+}
+
+fn align(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    }
+}
+
+/// The largest width or precision a spec may ask for, so a script can't make the host
+/// allocate without bound.
+pub const MAX_WIDTH: usize = u16::MAX as usize;
+
+fn number<'a>(s: &'a str, what: &str) -> Result<(Option<usize>, &'a str), String> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, rest) = s.split_at(end);
+    if digits.is_empty() {
+        return Ok((None, rest));
+    }
+    match digits.parse::<usize>() {
+        Ok(n) if n <= MAX_WIDTH => Ok((Some(n), rest)),
+        _ => Err(format!("{what} {digits} is above the limit of {MAX_WIDTH}")),
+    }
+}
+
+impl Spec {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut spec = Spec::default();
+        let mut chars = src.chars();
+        let mut rest = src;
+
+        match (chars.next(), chars.next().and_then(align)) {
+            (Some(fill), Some(a)) => {
+                spec.fill = fill;
+                spec.align = Some(a);
+                rest = &src[fill.len_utf8() + 1..];
+            }
+            (Some(c), _) if align(c).is_some() => {
+                spec.align = align(c);
+                rest = &src[1..];
+            }
+            _ => (),
+        }
+
+        if let Some(r) = rest.strip_prefix('0') {
+            spec.zero = true;
+            rest = r;
+        }
+
+        let (width, r) = number(rest, "width")?;
+        spec.width = width.unwrap_or(0);
+        rest = r;
+
+        if let Some(r) = rest.strip_prefix('.') {
+            let (precision, r) = number(r, "precision")?;
+            spec.precision = Some(precision.ok_or(format!("missing precision in `{src}`"))?);
+            rest = r;
+        }
+
+        match rest {
+            "" => Ok(spec),
+            _ => Err(format!("invalid format spec `{src}`")),
+        }
+    }
+
+    pub fn apply(&self, value: &Node) -> String {
+        let numeric = matches!(value, Node::Integer { .. } | Node::Float { .. });
+        let text = match (value, self.precision) {
+            (Node::Float { val, .. }, Some(p)) => format!("{val:.p$}"),
+            (Node::String { val, .. }, Some(p)) => val.chars().take(p).collect(),
+            (value, _) => value.inspect(),
+        };
+
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+        let pad = self.width - len;
+
+        // Zero padding goes between a number's sign and its digits.
+        if self.zero && numeric && self.align.is_none() {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return format!("{sign}{}{digits}", "0".repeat(pad));
+        }
+
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+        match self.align.unwrap_or(match numeric {
+            true => Align::Right,
+            false => Align::Left,
+        }) {
+            Align::Left => format!("{text}{}", fill(pad)),
+            Align::Right => format!("{}{text}", fill(pad)),
+            Align::Center => format!("{}{text}{}", fill(pad / 2), fill(pad - pad / 2)),
+        }
+    }
+}
+
+/// A piece of a format string: text, or a placeholder with an optional argument index.
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    Hole(Option<usize>, Spec),
+}
+
+fn pieces(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                text.push(c);
+            }
+            ('{', _) => {
+                let mut hole = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => hole.push(c),
+                        None => return Err("unclosed `{` in format string".to_string()),
+                    }
+                }
+                let (index, spec) = hole.split_once(':').unwrap_or((&hole, ""));
+                let index = match index.trim() {
+                    "" => None,
+                    i => Some(
+                        i.parse()
+                            .map_err(|_| format!("invalid argument index `{i}`"))?,
+                    ),
+                };
+
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(Piece::Hole(index, Spec::parse(spec)?));
+            }
+            ('}', _) => return Err("unmatched `}` in format string, use `}}`".to_string()),
+            (c, _) => text.push(c),
+        }
+    }
+
+    pieces.push(Piece::Text(text));
+    Ok(pieces)
+}
+
+/// Fills the placeholders in `template` with `values`, like `format!` in Rust.
+pub fn format(template: &str, values: &[Node]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut next = 0;
+
+    for piece in pieces(template)? {
+        match piece {
+            Piece::Text(text) => out.push_str(&text),
+            Piece::Hole(index, spec) => {
+                let i = index.unwrap_or_else(|| {
+                    next += 1;
+                    next - 1
+                });
+                let value = values.get(i).ok_or(format!(
+                    "the format string uses argument {i}, but only {} were given",
+                    values.len()
+                ))?;
+                out.push_str(&spec.apply(value));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+// The characters of a placeholder's expression outside its string literals, which may be
+// quoted as `"..."` or, escaped in the f-string, as `\"...\"`.
+fn outside_strings(src: &str) -> Vec<(usize, char)> {
+    let mut out = vec![];
+    let mut close: Option<&str> = None;
+    let mut i = 0;
+    while let Some(c) = src[i..].chars().next() {
+        let rest = &src[i..];
+        match close {
+            Some(quote) if rest.starts_with(quote) => {
+                close = None;
+                i += quote.len();
+            }
+            Some(_) if c == '\\' => i += escape_len(rest),
+            Some(_) => i += c.len_utf8(),
+            None => match ["\\\"", "\""].into_iter().find(|q| rest.starts_with(q)) {
+                Some(quote) => {
+                    close = Some(quote);
+                    i += quote.len();
+                }
+                None => {
+                    out.push((i, c));
+                    i += c.len_utf8();
+                }
+            },
+        }
+    }
+    out
+}
+
+// Finds the `}` closing the placeholder `src` starts in, skipping any braces of objects
+// and strings within it.
+fn closing_brace(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in outside_strings(src) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+fn top_level_colon(src: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in outside_strings(src) {
+        match c {
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            ':' if depth == 0 => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Turns the f-string `lexeme`, starting at `start` (line and column), into a call to
+/// `format` with the expressions in its placeholders as arguments. Those expressions may
+/// contain string literals, as in `f"{m.get("k")}"`.
+pub fn fstring(lexeme: &str, start: (usize, usize), span: Spans) -> Result<Node, FangErr> {
+    let error = |message: String| FangErr::SyntaxError {
+        span: span.clone(),
+        message,
+        hints: vec![],
+    };
+
+    // Skip the `f"` and leave off the closing quote.
+    let body = &lexeme[2..lexeme.len() - 1];
+    let mut template = String::new();
+    let mut args = vec![];
+    let mut i = 0;
+
+    while i < body.len() {
+        let rest = &body[i..];
+        let c = rest.chars().next().unwrap();
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            template.push_str(&rest[..2]);
+            i += 2;
+            continue;
+        }
+        if c == '}' {
+            return Err(error("unmatched `}` in f-string, use `}}`".to_string()));
+        }
+        // Escaped braces are literal, so they're doubled in the template.
+        if c == '\\' {
+            let len = escape_len(rest);
+            let text = unescape(&rest[..len]).map_err(error)?;
+            template.push_str(&text.replace('{', "{{").replace('}', "}}"));
+            i += len;
+            continue;
+        }
+        if c != '{' {
+            template.push(c);
+            i += c.len_utf8();
+            continue;
+        }
+
+        let hole = &rest[1..];
+        let end = closing_brace(hole).ok_or(error("unclosed `{` in f-string".to_string()))?;
+        let (expr, spec) = match top_level_colon(&hole[..end]) {
+            Some(colon) => (&hole[..colon], &hole[colon + 1..end]),
+            None => (&hole[..end], ""),
+        };
+        Spec::parse(spec).map_err(error)?;
+
+        // The expression is parsed on its own, padded so its locations match the file.
+        let before = &lexeme[..2 + i + 1];
+        let line = start.0 + before.matches('\n').count();
+        let col = match before.rfind('\n') {
+            Some(nl) => before[nl + 1..].chars().count(),
+            None => start.1 - 1 + before.chars().count(),
+        };
+        let expr = unescape(expr).map_err(error)?;
+        let src = format!("{}{}{expr};", "\n".repeat(line - 1), " ".repeat(col));
+
+        match parse(&src).map_err(|_| error(format!("invalid expression `{expr}` in f-string")))?[..]
+        {
+            [ref node] if !matches!(node, Node::Function { .. } | Node::Empty) => {
+                args.push(node.clone())
+            }
+            _ => return Err(error(format!("invalid expression `{expr}` in f-string"))),
+        }
+
+        template.push_str(&format!("{{:{spec}}}"));
+        i += 1 + end + 1;
+    }
+
+    args.insert(
+        0,
+        Node::String {
+            val: template,
+            span: span.clone(),
+        },
+    );
+    Ok(Node::Call {
+        name: "format".to_string(),
+        args: Box::new(args),
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::Engine, sandbox::Profile, value::Value};
+
+    fn int(val: u64) -> Node {
+        Node::Integer {
+            val,
+            span: Spans::empty(),
+        }
+    }
+
+    #[test]
+    fn specs_pad_and_round() {
+        let float = Node::Float {
+            val: 1.23456,
+            span: Spans::empty(),
+        };
+        assert_eq!(
+            format("{:.2}|{:>6}|{:<4}|", &[float, int(42), int(7)]).unwrap(),
+            "1.23|    42|7   |"
+        );
+        assert_eq!(
+            format("{1}{0} {:*^5} {:03}", &[int(1), int(2)]).unwrap(),
+            "21 **1** 002"
+        );
+        assert_eq!(format("{{}}", &[]).unwrap(), "{}");
+        // Like Rust, precision only applies to floats and strings.
+        assert_eq!(
+            format("{:.2}|{:>5.1}", &[int(3), int(4)]).unwrap(),
+            "3|    4"
+        );
+        assert!(format("{} {}", &[int(1)]).is_err());
+        assert!(format("{:x}", &[int(1)]).is_err());
+    }
+
+    #[test]
+    fn widths_are_bounded() {
+        assert_eq!(format("{:>65535}", &[int(1)]).unwrap().len(), MAX_WIDTH);
+        assert!(Spec::parse(".65536").is_err());
+        assert!(Spec::parse("99999999999999999999999").is_err());
+
+        let mut engine = Engine::new().with_profile(Profile::untrusted());
+        let err = engine.eval("format(\"{:>9999999999999}\", 1)").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::InvalidFormat { .. }));
+        assert!(engine.parse("f\"{1:.9999999999999}\";").is_err());
+    }
+
+    #[test]
+    fn fstrings_interpolate_expressions() {
+        let mut engine = Engine::new();
+        engine.set_global("name", Value::String("Ada".to_string()));
+        engine.set_global("age", Value::Int(36));
+        assert_eq!(
+            engine
+                .eval("f\"Hello {name}, you are {age + 1:>4}\"")
+                .unwrap(),
+            Value::String("Hello Ada, you are   37".to_string())
+        );
+        assert_eq!(
+            engine.eval("format(\"{} {}\", 1.5, true)").unwrap(),
+            Value::String("1.5 true".to_string())
+        );
+        assert_eq!(
+            engine
+                .eval("f\"{\"a\"} {name.replace(\"A\", \"}\"):>4}\"")
+                .unwrap(),
+            Value::String("a  }da".to_string())
+        );
+        assert!(engine.parse("f\"{age +}\";").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    ast::{Node, Spans},
+    errs::FangErr,
+    native::native,
+    sandbox::{gate, Capability},
+    value::Value,
+};
+
+fn io(path: &str, e: io::Error) -> FangErr {
+    FangErr::Io {
+        span: Spans::empty(),
+        path: path.to_string(),
+        message: e.to_string(),
+    }
+}
+
+fn append(path: &str, contents: &str) -> io::Result<()> {
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+fn read_dir(path: &str) -> io::Result<Vec<String>> {
+    let mut names = fs::read_dir(path)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<io::Result<Vec<String>>>()?;
+    names.sort();
+    Ok(names)
+}
+
+// Directories are only removed when empty, so a typo can't take a whole tree with it.
+fn remove(path: &str) -> io::Result<()> {
+    match Path::new(path).is_dir() {
+        true => fs::remove_dir(path),
+        false => fs::remove_file(path),
+    }
+}
+
+fn metadata(path: &str) -> io::Result<Value> {
+    let meta = fs::metadata(path)?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+
+    Ok(Value::Object {
+        typed: "Metadata".to_string(),
+        fields: vec![
+            ("size".to_string(), Value::Int(meta.len())),
+            ("is_file".to_string(), Value::Bool(meta.is_file())),
+            ("is_dir".to_string(), Value::Bool(meta.is_dir())),
+            (
+                "readonly".to_string(),
+                Value::Bool(meta.permissions().readonly()),
+            ),
+            ("modified".to_string(), Value::Int(modified)),
+        ],
+    })
+}
+
+fn field(name: &str, value: Node) -> Node {
+    Node::Field {
+        name: name.to_string(),
+        value: Box::new(value),
+        span: Spans::empty(),
+    }
+}
+
+/// The `fs` object, needing the `fs` capability.
+pub fn module() -> Node {
+    let fields = vec![
+        field(
+            "read_to_string",
+            native("read_to_string", |path: String| {
+                fs::read_to_string(&path).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "write",
+            native("write", |path: String, contents: String| {
+                fs::write(&path, contents).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "append",
+            native("append", |path: String, contents: String| {
+                append(&path, &contents).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "exists",
+            native("exists", |path: String| Path::new(&path).exists()),
+        ),
+        field(
+            "read_dir",
+            native("read_dir", |path: String| {
+                read_dir(&path).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "create_dir_all",
+            native("create_dir_all", |path: String| {
+                fs::create_dir_all(&path).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "remove",
+            native("remove", |path: String| {
+                remove(&path).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "metadata",
+            native("metadata", |path: String| {
+                metadata(&path).map_err(|e| io(&path, e))
+            }),
+        ),
+        field(
+            "is_file",
+            native("is_file", |path: String| Path::new(&path).is_file()),
+        ),
+        field(
+            "is_dir",
+            native("is_dir", |path: String| Path::new(&path).is_dir()),
+        ),
+    ];
+
+    gate(
+        Capability::Fs,
+        Node::Object {
+            typed: "<Internal>".to_string(),
+            fields: Box::new(fields),
+            span: Spans::empty(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::Engine, sandbox::Profile};
+
+    #[test]
+    fn reads_and_writes_files() {
+        let dir = std::env::temp_dir().join(format!("fang-fs-{}", std::process::id()));
+        let dir = dir.to_string_lossy().replace('\\', "/");
+        let mut engine = Engine::new();
+        engine.set_global("dir", Value::String(dir));
+
+        engine
+            .eval("fs.create_dir_all(dir);\nlet path = dir + \"/notes.txt\";\nfs.write(path, \"a\");\nfs.append(path, \"b\");")
+            .unwrap();
+        assert_eq!(
+            engine.eval("fs.read_to_string(path)").unwrap(),
+            Value::String("ab".to_string())
+        );
+        assert_eq!(engine.eval("fs.exists(path)").unwrap(), Value::Bool(true));
+        engine.eval("let meta = fs.metadata(path);").unwrap();
+        assert_eq!(engine.eval("meta.size").unwrap(), Value::Int(2));
+        engine.eval("let names = fs.read_dir(dir);").unwrap();
+        assert_eq!(
+            engine.eval("names.0").unwrap(),
+            Value::String("notes.txt".to_string())
+        );
+
+        engine.eval("fs.remove(path);\nfs.remove(dir);").unwrap();
+        assert_eq!(engine.eval("fs.exists(dir)").unwrap(), Value::Bool(false));
+        let err = engine.eval("fs.read_to_string(path)").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::Io { span, .. } if !span.is_empty()));
+    }
+
+    #[test]
+    fn needs_the_fs_capability() {
+        let mut engine = Engine::new().with_profile(Profile::untrusted());
+        let err = engine.eval("fs.exists(\"Cargo.toml\")").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::PermissionDenied { .. }));
+    }
+}
@@ -0,0 +1,57 @@
+//! Fang, an embeddable scripting language.
+//!
+//! ```
+//! use fang::{Engine, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.set_global("width", Value::Int(6));
+//! assert_eq!(engine.eval("width * 7").unwrap(), Value::Int(42));
+//!
+//! engine.register_fn("area", |w: u64, h: u64| w * h);
+//! assert_eq!(engine.eval("area(width, 2)").unwrap(), Value::Int(12));
+//! ```
+
+use std::sync::Mutex;
+
+use lrlex::lrlex_mod;
+use lrpar::lrpar_mod;
+use once_cell::sync::Lazy;
+
+lrlex_mod!("fang.l");
+lrpar_mod!("fang.y");
+
+pub mod ast;
+pub mod bytecode;
+pub mod console;
+pub mod disasm;
+pub mod engine;
+pub mod errs;
+pub mod explain;
+pub mod fmt;
+pub mod format;
+pub mod fs;
+pub mod limits;
+pub mod lint;
+pub mod math;
+pub mod native;
+pub mod optimise;
+pub mod os;
+pub mod parser;
+pub mod repl;
+pub mod sandbox;
+pub mod scope;
+pub mod strings;
+pub mod value;
+
+#[cfg(test)]
+mod testing;
+
+pub use engine::Engine;
+pub use errs::FangErr;
+pub use native::{FromFang, IntoArgs, IntoFang};
+pub use value::Value;
+
+/// Name of the file being run, used when printing source locations.
+pub static FILE_NAME: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+/// Arguments given after the script on the command line.
+pub static SCRIPT_ARGS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
@@ -0,0 +1,151 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    ast::{Node, Spans},
+    bytecode::Op,
+    errs::FangErr,
+    scope::Scope,
+};
+
+// Every non-tail call nests a Rust-level `eval_bytecode`, so this is kept well under
+// what fits in the main thread's stack on a debug build.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Resource limits shared by every scope of a single run.
+#[derive(Debug)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_ops: Option<u64>,
+    pub max_heap: Option<usize>,
+    depth: Cell<usize>,
+    ops: Cell<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DEPTH, None, None)
+    }
+}
+
+impl Limits {
+    pub fn new(max_depth: usize, max_ops: Option<u64>, max_heap: Option<usize>) -> Self {
+        Self {
+            max_depth,
+            max_ops,
+            max_heap,
+            depth: Cell::new(0),
+            ops: Cell::new(0),
+        }
+    }
+
+    pub fn executed(&self) -> u64 {
+        self.ops.get()
+    }
+
+    /// Counts `op` against the execution budget.
+    pub fn tick(&self, op: &Op, scope: &Scope) -> Result<(), FangErr> {
+        self.ops.set(self.ops.get() + 1);
+
+        match self.max_ops {
+            Some(budget) if self.ops.get() > budget => Err(FangErr::ExecutionBudgetExceeded {
+                span: op.span().unwrap_or_else(Spans::empty),
+                budget,
+                scope: scope.name.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enters a call frame, which is left again when the returned guard is dropped.
+    pub fn enter(self: &Rc<Self>, op: &Op, scope: &Scope) -> Result<Frame, FangErr> {
+        if self.depth.get() >= self.max_depth {
+            return Err(FangErr::StackOverflow {
+                span: op.span().unwrap_or_else(Spans::empty),
+                depth: self.max_depth,
+                scope: scope.name.clone(),
+            });
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        Ok(Frame(self.clone()))
+    }
+
+    /// Checks the values held by `scope`, its parents and `stack` against the heap limit.
+    pub fn check_heap(&self, op: &Op, scope: &Scope, stack: &[Node]) -> Result<(), FangErr> {
+        let Some(limit) = self.max_heap else {
+            return Ok(());
+        };
+
+        let used = scope.heap_size() + stack.iter().map(Node::heap_size).sum::<usize>();
+        if used > limit {
+            return Err(FangErr::HeapLimitExceeded {
+                span: op.span().unwrap_or_else(Spans::empty),
+                limit,
+                used,
+                scope: scope.name.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Frame(Rc<Limits>);
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        self.0.depth.set(self.0.depth.get() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bytecode::eval_bytecode, scope::global_scope, testing::parse};
+
+    // Every error here is raised inside `f`, so drop the call stack it unwound through.
+    fn run(src: &str, limits: Limits) -> Result<Option<Node>, FangErr> {
+        let mut scope = global_scope();
+        scope.limits = Rc::new(limits);
+        eval_bytecode(parse(src), &mut scope).map_err(|e| match e {
+            FangErr::Traced { err, .. } => *err,
+            e => e,
+        })
+    }
+
+    #[test]
+    fn tail_calls_run_until_the_budget() {
+        let res = run(
+            "fn f(n: int): int { return f(n + 1); } f(0);",
+            Limits::new(DEFAULT_MAX_DEPTH, Some(100_000), None),
+        );
+
+        assert!(matches!(
+            res,
+            Err(FangErr::ExecutionBudgetExceeded {
+                budget: 100_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn nested_calls_overflow() {
+        let res = run(
+            "fn f(n: int): int { return 1 + f(n + 1); } f(0);",
+            Limits::new(32, None, None),
+        );
+
+        assert!(matches!(res, Err(FangErr::StackOverflow { depth: 32, .. })));
+    }
+
+    #[test]
+    fn heap_limit() {
+        let res = run(
+            r#"fn f(s: string): string { return f(s + s); } f("fang");"#,
+            Limits::new(DEFAULT_MAX_DEPTH, None, Some(64 * 1024)),
+        );
+
+        assert!(matches!(res, Err(FangErr::HeapLimitExceeded { .. })));
+    }
+}
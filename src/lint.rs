@@ -0,0 +1,536 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    ast::{Node, Spans},
+    errs::{json_span, json_str},
+    FILE_NAME,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lint {
+    UnusedVariables,
+    UnusedFunctions,
+    UnusedParameters,
+    UnreachableCode,
+    Shadowing,
+    UnusedImpls,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint::UnusedVariables,
+    Lint::UnusedFunctions,
+    Lint::UnusedParameters,
+    Lint::UnreachableCode,
+    Lint::Shadowing,
+    Lint::UnusedImpls,
+];
+
+impl Lint {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "W0001",
+            Lint::UnusedFunctions => "W0002",
+            Lint::UnusedParameters => "W0003",
+            Lint::UnreachableCode => "W0004",
+            Lint::Shadowing => "W0005",
+            Lint::UnusedImpls => "W0006",
+        }
+    }
+
+    /// The name used by `// fang:allow(...)`, `--allow` and `-D`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedFunctions => "unused_functions",
+            Lint::UnusedParameters => "unused_parameters",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::Shadowing => "shadowing",
+            Lint::UnusedImpls => "unused_impls",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "Unused variable",
+            Lint::UnusedFunctions => "Unused function",
+            Lint::UnusedParameters => "Unused parameter",
+            Lint::UnreachableCode => "Unreachable code",
+            Lint::Shadowing => "Shadowed name",
+            Lint::UnusedImpls => "Unused implementation",
+        }
+    }
+
+    /// Whether `names` refers to this lint, either directly or via the `unused` and `warnings` groups.
+    pub fn matches<S: AsRef<str>>(&self, names: &[S]) -> bool {
+        names.iter().any(|n| match n.as_ref() {
+            "warnings" => true,
+            "unused" => matches!(
+                self,
+                Lint::UnusedVariables
+                    | Lint::UnusedFunctions
+                    | Lint::UnusedParameters
+                    | Lint::UnusedImpls
+            ),
+            n => n == self.name(),
+        })
+    }
+
+    pub fn is_known(name: &str) -> bool {
+        matches!(name, "warnings" | "unused") || LINTS.iter().any(|l| l.name() == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Warning {
+    pub lint: Lint,
+    pub span: Spans,
+    pub message: String,
+    pub previous: Option<Spans>,
+}
+
+impl Warning {
+    /// Renders the warning as a single line JSON object, in the same shape as `FangErr::to_json`.
+    pub fn to_json(&self, denied: bool) -> String {
+        let related = match &self.previous {
+            Some(span) => format!(
+                r#"{{"message":"previously declared here","span":{}}}"#,
+                json_span(span)
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            r#"{{"code":{},"severity":{},"title":{},"message":{},"file":{},"span":{},"related":[{}],"hints":[],"trace":[]}}"#,
+            json_str(self.lint.code()),
+            json_str(if denied { "error" } else { "warning" }),
+            json_str(self.lint.title()),
+            json_str(&self.message),
+            json_str(&FILE_NAME.lock().unwrap()),
+            json_span(&self.span),
+            related
+        )
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "[{} {}]: {}\n{}",
+            self.lint.code(),
+            self.lint.title(),
+            self.message,
+            self.span.snippet()
+        )?;
+        if let Some(previous) = &self.previous {
+            write!(f, "\n\n{}", previous.label("previously declared here"))?;
+        }
+        write!(
+            f,
+            "\nnote: add `// fang:allow({})` to silence this warning",
+            self.lint.name()
+        )
+    }
+}
+
+/// A `// fang:allow(...)` comment. On a line of its own it covers the statement that follows,
+/// after code it covers the statement on that line.
+struct Allow {
+    line: usize,
+    names: Vec<String>,
+}
+
+fn allows(src: &str) -> Vec<Allow> {
+    let lines = src.lines().collect::<Vec<&str>>();
+    let mut allows = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let Some(start) = line.find("// fang:allow(") else {
+            continue;
+        };
+        let rest = &line[start + "// fang:allow(".len()..];
+        let Some(end) = rest.find(')') else {
+            continue;
+        };
+        let names = rest[..end]
+            .split(',')
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .collect();
+
+        let target = match line[..start].trim().is_empty() {
+            true => lines[i + 1..]
+                .iter()
+                .position(|l| !l.trim().is_empty() && !l.trim().starts_with("//"))
+                .map(|n| i + 1 + n),
+            false => Some(i),
+        };
+
+        if let Some(target) = target {
+            allows.push(Allow {
+                line: target + 1,
+                names,
+            });
+        }
+    }
+
+    allows
+}
+
+struct Binding {
+    name: String,
+    lint: Lint,
+    span: Spans,
+    allowed: Vec<String>,
+    used: bool,
+}
+
+struct Impl {
+    name: String,
+    methods: Vec<String>,
+    span: Spans,
+    allowed: Vec<String>,
+}
+
+struct Linter {
+    allows: Vec<Allow>,
+    active: Vec<String>,
+    scopes: Vec<Vec<Binding>>,
+    functions: Vec<String>,
+    impls: Vec<Impl>,
+    methods: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+/// Statically checks `ast` for likely mistakes that don't stop it from running.
+pub fn lint(src: &str, ast: &[Node]) -> Vec<Warning> {
+    let mut linter = Linter {
+        allows: allows(src),
+        active: vec![],
+        scopes: vec![],
+        functions: vec![],
+        impls: vec![],
+        methods: HashSet::new(),
+        warnings: vec![],
+    };
+
+    linter.block(ast, vec![]);
+
+    for imp in std::mem::take(&mut linter.impls) {
+        if !imp.methods.iter().any(|m| linter.methods.contains(m)) {
+            linter.warn(
+                Lint::UnusedImpls,
+                imp.span,
+                format!("{} is never used", imp.name),
+                None,
+                &imp.allowed,
+            );
+        }
+    }
+
+    linter
+        .warnings
+        .sort_by_key(|w| (w.span.line(), w.span.col()));
+    linter.warnings
+}
+
+impl Linter {
+    fn warn(
+        &mut self,
+        lint: Lint,
+        span: Spans,
+        message: String,
+        previous: Option<Spans>,
+        allowed: &[String],
+    ) {
+        if !lint.matches(allowed) {
+            self.warnings.push(Warning {
+                lint,
+                span,
+                message,
+                previous,
+            });
+        }
+    }
+
+    fn bind(&mut self, name: &str, lint: Lint, span: &Spans) {
+        let binding = Binding {
+            name: name.to_string(),
+            lint,
+            span: span.clone(),
+            allowed: self.active.clone(),
+            used: false,
+        };
+        self.scopes.last_mut().unwrap().push(binding);
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|s| s.iter_mut().rev())
+            .find(|b| b.name == name)
+    }
+
+    // Only the part before the first `.` can be a binding, the rest is a field or method.
+    fn use_name(&mut self, name: &str) {
+        let root = name.split('.').next().unwrap_or(name);
+        if self.functions.last().is_some_and(|f| f == root) {
+            return;
+        }
+        if let Some(binding) = self.lookup(root) {
+            binding.used = true;
+        }
+    }
+
+    fn block(&mut self, body: &[Node], params: Vec<(String, Spans)>) {
+        self.scopes.push(vec![]);
+        for (name, span) in params {
+            self.bind(&name, Lint::UnusedParameters, &span);
+        }
+
+        // Functions are looked up when called rather than where they're declared, so any
+        // function in the block may be called from anywhere in it.
+        for node in body {
+            if let Node::Function { name, span, .. } = node {
+                let saved = self.enter_statement(node);
+                self.bind(name, Lint::UnusedFunctions, span);
+                self.active = saved;
+            }
+        }
+
+        let mut returned = false;
+        for node in body {
+            let saved = self.enter_statement(node);
+            if returned && !matches!(node, Node::Empty) {
+                let allowed = self.active.clone();
+                self.warn(
+                    Lint::UnreachableCode,
+                    node.span(),
+                    "Statement after return is never run".to_string(),
+                    None,
+                    &allowed,
+                );
+                returned = false;
+            }
+            if matches!(node, Node::Return { .. }) {
+                returned = true;
+            }
+
+            self.statement(node);
+            self.active = saved;
+        }
+
+        for b in self.scopes.pop().unwrap() {
+            if b.used || b.name.starts_with('_') || b.name == "self" {
+                continue;
+            }
+            let message = match b.lint {
+                Lint::UnusedFunctions => format!("Function {} is never called", b.name),
+                Lint::UnusedParameters => format!("Parameter {} is never used", b.name),
+                _ => format!("Variable {} is never used", b.name),
+            };
+            self.warn(b.lint, b.span, message, None, &b.allowed);
+        }
+    }
+
+    fn enter_statement(&mut self, node: &Node) -> Vec<String> {
+        let saved = self.active.clone();
+        let line = node.span().line();
+        for allow in self.allows.iter().filter(|a| a.line == line) {
+            self.active.extend(allow.names.iter().cloned());
+        }
+        saved
+    }
+
+    fn function(&mut self, name: &str, args: &[Node], body: &[Node]) {
+        let params = args
+            .iter()
+            .filter_map(|a| match a {
+                Node::TypedVariable { name, span, .. } => Some((name.clone(), span.clone())),
+                _ => None,
+            })
+            .collect();
+
+        self.functions.push(name.to_string());
+        self.block(body, params);
+        self.functions.pop();
+    }
+
+    fn statement(&mut self, node: &Node) {
+        match node {
+            Node::Declaration {
+                name, rhs, span, ..
+            } => {
+                if let Some(rhs) = rhs {
+                    self.statement(rhs);
+                }
+
+                let outer = self.scopes[..self.scopes.len() - 1]
+                    .iter()
+                    .rev()
+                    .flat_map(|s| s.iter().rev())
+                    .find(|b| b.name == *name && b.lint != Lint::UnusedFunctions)
+                    .map(|b| b.span.clone());
+                if let Some(previous) = outer {
+                    let allowed = self.active.clone();
+                    self.warn(
+                        Lint::Shadowing,
+                        span.clone(),
+                        format!("Variable {name} shadows an outer declaration"),
+                        Some(previous),
+                        &allowed,
+                    );
+                }
+
+                self.bind(name, Lint::UnusedVariables, span);
+            }
+            Node::Assignment { rhs, .. } => self.statement(rhs),
+            Node::Identifier { val, .. } => self.use_name(val),
+            Node::Call { name, args, .. } => {
+                for arg in args.iter() {
+                    self.statement(arg);
+                }
+                self.use_name(name);
+                if let Some((_, method)) = name.rsplit_once('.') {
+                    self.methods.insert(method.to_string());
+                }
+            }
+            Node::Function {
+                name, args, body, ..
+            } => self.function(name, args, body),
+            Node::TraitImpl {
+                trait_name,
+                type_name,
+                fields,
+                span,
+            } => {
+                let mut methods = vec![];
+                for field in fields.iter() {
+                    if let Node::Function {
+                        name, args, body, ..
+                    } = field
+                    {
+                        methods.push(name.clone());
+                        self.function(name, args, body);
+                    }
+                }
+
+                self.impls.push(Impl {
+                    name: format!("impl {trait_name} for {type_name}"),
+                    methods,
+                    span: span.clone(),
+                    allowed: self.active.clone(),
+                });
+            }
+            Node::Object { fields, .. } => {
+                for field in fields.iter() {
+                    self.statement(field);
+                }
+            }
+            Node::Field { value, .. } => self.statement(value),
+            Node::Return { value, .. } => self.statement(value),
+            Node::Add { lhs, rhs, .. }
+            | Node::Subtract { lhs, rhs, .. }
+            | Node::Multiply { lhs, rhs, .. }
+            | Node::Divide { lhs, rhs, .. }
+            | Node::Modulo { lhs, rhs, .. } => {
+                self.statement(lhs);
+                self.statement(rhs);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::parse;
+
+    fn lints(src: &str) -> Vec<(&'static str, usize)> {
+        lint(src, &parse(src))
+            .into_iter()
+            .map(|w| (w.lint.name(), w.span.line()))
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables_and_functions() {
+        assert_eq!(
+            lints("let a = 1;\nlet b = a;\nfn f(): int { return 1; }\nconsole.log(b);"),
+            vec![("unused_functions", 3)]
+        );
+        assert_eq!(lints("let a = 1;"), vec![("unused_variables", 1)]);
+        assert_eq!(lints("let _a = 1;"), vec![]);
+    }
+
+    #[test]
+    fn recursion_is_not_a_use() {
+        assert_eq!(
+            lints("fn f(n: int): int { return f(n); }"),
+            vec![("unused_functions", 1)]
+        );
+        assert_eq!(
+            lints("let x = g(1);\nfn g(n: int): int { return n; }\nconsole.log(x);"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn unused_parameters() {
+        assert_eq!(
+            lints("fn f(a: int, b: int): int { return a; }\nconsole.log(f(1, 2));"),
+            vec![("unused_parameters", 1)]
+        );
+    }
+
+    #[test]
+    fn unreachable_code() {
+        assert_eq!(
+            lints("fn f(): int {\n  return 1;\n  console.log(2);\n}\nconsole.log(f());"),
+            vec![("unreachable_code", 3)]
+        );
+    }
+
+    #[test]
+    fn shadowing() {
+        assert_eq!(
+            lints("let a = 1;\nfn f(): int {\n  let a = 2;\n  return a;\n}\nconsole.log(f() + a);"),
+            vec![("shadowing", 3)]
+        );
+    }
+
+    #[test]
+    fn unused_impls() {
+        let src = "impl Show for Point { fn show(self): string { return \"p\"; } };";
+        assert_eq!(lints(src), vec![("unused_impls", 1)]);
+        assert_eq!(
+            lints(&format!(
+                "{src}\nlet p = Point {{}};\nconsole.log(p.show());"
+            )),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn allow_comments() {
+        assert_eq!(lints("// fang:allow(unused)\nlet a = 1;"), vec![]);
+        assert_eq!(lints("let a = 1; // fang:allow(unused_variables)"), vec![]);
+        assert_eq!(
+            lints("// fang:allow(shadowing)\nlet a = 1;"),
+            vec![("unused_variables", 2)]
+        );
+        assert_eq!(
+            lints("// fang:allow(unused)\nfn f(a: int): int {\n  let b = 1;\n  return 1;\n}"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn groups() {
+        assert!(Lint::UnusedParameters.matches(&["unused"]));
+        assert!(!Lint::Shadowing.matches(&["unused"]));
+        assert!(Lint::Shadowing.matches(&["warnings"]));
+    }
+}
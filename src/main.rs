@@ -1,63 +1,359 @@
 use std::{
-    env::args, fs::{self, File}, io::Read, path::Path, sync::Mutex
+    env::args,
+    fs,
+    io::{self, Read},
+    path::Path,
+    process::exit,
+    str::FromStr,
 };
 
-use bytecode::eval_bytecode;
-use lrlex::lrlex_mod;
-use lrpar::lrpar_mod;
-use once_cell::sync::Lazy;
-use scope::GLOBAL_SCOPE;
+use cli::{Cli, Command, Source, USAGE};
+use fang::{
+    ast::{Node, Spans},
+    disasm::disassemble,
+    errs::{FangErr, EXIT_SYNTAX},
+    explain::{explain, EXPLANATIONS},
+    fmt::format,
+    lint::{lint, Lint},
+    repl::Repl,
+    sandbox::{Capability, Profile},
+    Engine, Value, FILE_NAME, SCRIPT_ARGS,
+};
 
-lrlex_mod!("fang.l");
-lrpar_mod!("fang.y");
+mod cli;
 
-pub mod ast;
-pub mod bytecode;
-pub mod errs;
-pub mod scope;
+fn flag<T: FromStr>(flags: &[String], name: &str) -> Option<T> {
+    let value = flags
+        .iter()
+        .find_map(|f| f.strip_prefix(name)?.strip_prefix('='))?;
 
-pub const FILE_NAME: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+    match value.parse() {
+        Ok(v) => Some(v),
+        Err(_) => {
+            eprintln!("Invalid value for {name}: {value}");
+            exit(1);
+        }
+    }
+}
 
-fn main() {
-    let args: Vec<String> = args().collect();
-    if args.len() < 1 {
-        eprintln!("Usage: fang <source file>");
-        std::process::exit(1);
+fn report(err: &FangErr, json: bool) {
+    match json {
+        true => eprintln!("{}", err.to_json()),
+        false => eprintln!("{}\n", err),
     }
+}
 
-    let input = {
-        let mut s = String::new();
-        let p = Path::new(&args[1]);
-        let mut f = File::open(p).unwrap();
-        f.read_to_string(&mut s).unwrap();
+// Every `-D <lint>` / `--deny=<lint>` (or `-A` / `--allow`) flag, validated against the known lints.
+fn lint_flags(flags: &[String], short: &str, long: &str) -> Vec<String> {
+    let names = flags
+        .iter()
+        .filter_map(|f| f.strip_prefix(short).or_else(|| f.strip_prefix(long)))
+        .map(String::from)
+        .collect::<Vec<String>>();
+
+    if let Some(unknown) = names.iter().find(|n| !Lint::is_known(n)) {
+        eprintln!("Unknown lint {unknown}");
+        exit(1);
+    }
 
-        FILE_NAME.lock().unwrap().push_str(p.file_name().unwrap().to_str().unwrap());
+    names
+}
 
-        s
+// `--sandbox` starts from the untrusted profile, then `--allow-cap` / `--deny-cap` and the
+// limit flags adjust it.
+fn profile(cli: &Cli) -> Profile {
+    let mut profile = match cli.has_flag("--sandbox") {
+        true => Profile::untrusted(),
+        false => Profile::trusted(),
     };
 
-    let def = fang_l::lexerdef();
-    let lexer = def.lexer(&input);
-    let (res, err) = fang_y::parse(&lexer);
+    for f in &cli.flags {
+        let (allow, name) = match (
+            f.strip_prefix("--allow-cap="),
+            f.strip_prefix("--deny-cap="),
+        ) {
+            (Some(name), _) => (true, name),
+            (_, Some(name)) => (false, name),
+            _ => continue,
+        };
+        let Some(capability) = Capability::from_name(name) else {
+            eprintln!("Unknown capability {name}");
+            exit(1);
+        };
+        profile = match allow {
+            true => profile.allow(capability),
+            false => profile.deny(capability),
+        };
+    }
+
+    if let Some(depth) = flag(&cli.flags, "--max-depth") {
+        profile = profile.max_depth(depth);
+    }
+    if let Some(ops) = flag(&cli.flags, "--max-ops") {
+        profile = profile.max_ops(Some(ops));
+    }
+    if let Some(heap) = flag(&cli.flags, "--max-heap") {
+        profile = profile.max_heap(Some(heap));
+    }
+    profile
+}
+
+fn engine(cli: &Cli, name: &str) -> Engine {
+    Engine::new()
+        .optimised(cli.has_flag("-O"))
+        .with_profile(profile(cli))
+        .with_file_name(name)
+}
+
+/// Reads `source`, along with the file name to use in diagnostics.
+fn read(source: &Source) -> (String, String) {
+    let (name, src) = match source {
+        Source::File(path) => (
+            Path::new(path)
+                .file_name()
+                .map_or(path.clone(), |n| n.to_string_lossy().to_string()),
+            fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("Unable to read {path}: {e}");
+                exit(1);
+            }),
+        ),
+        Source::Stdin => {
+            let mut src = String::new();
+            io::stdin().read_to_string(&mut src).unwrap_or_else(|e| {
+                eprintln!("Unable to read stdin: {e}");
+                exit(1);
+            });
+            ("<stdin>".to_string(), src)
+        }
+        Source::Inline(src) => ("<inline>".to_string(), src.clone()),
+    };
+
+    (name, src)
+}
+
+fn parse_or_exit(engine: &Engine, src: &str, json: bool) -> Vec<Node> {
+    engine.parse(src).unwrap_or_else(|errs| {
+        for e in errs {
+            report(&e, json);
+        }
+        exit(EXIT_SYNTAX);
+    })
+}
+
+/// Prints the lint warnings for `src`, returning how many of them were denied.
+fn check(cli: &Cli, src: &str, ast: &[Node], json: bool) -> usize {
+    let allow = lint_flags(&cli.flags, "-A", "--allow=");
+    let deny = lint_flags(&cli.flags, "-D", "--deny=");
+
+    let mut denied = 0;
+    for w in lint(src, ast)
+        .into_iter()
+        .filter(|w| !w.lint.matches(&allow))
+    {
+        let deny = w.lint.matches(&deny);
+        denied += deny as usize;
+        match json {
+            true => eprintln!("{}", w.to_json(deny)),
+            false if deny => eprintln!("{w}\nnote: denied by -D\n"),
+            false => eprintln!("{w}\n"),
+        }
+    }
+
+    denied
+}
+
+fn fmt(cli: &Cli, json: bool) {
+    let mut unformatted = 0;
+    for source in &cli.sources {
+        let (name, src) = read(source);
+        *FILE_NAME.lock().unwrap() = name;
+        let formatted = format(&src).unwrap_or_else(|errs| {
+            for e in errs {
+                report(&e, json);
+            }
+            exit(EXIT_SYNTAX);
+        });
+
+        match source {
+            Source::File(path) if formatted != src => {
+                if cli.has_flag("--check") {
+                    println!("{path} is not formatted");
+                    unformatted += 1;
+                } else if let Err(e) = fs::write(path, formatted) {
+                    eprintln!("Unable to write {path}: {e}");
+                    exit(1);
+                }
+            }
+            Source::File(_) => (),
+            _ => print!("{formatted}"),
+        }
+    }
 
-    if err.len() > 0 {
-        eprintln!("Unable to parse:");
+    if unformatted > 0 {
+        exit(1);
     }
+}
+
+fn test_files(cli: &Cli) -> Vec<Source> {
+    let paths = match cli.sources.is_empty() {
+        true => vec![Source::File("tests".to_string())],
+        false => cli.sources.clone(),
+    };
 
-    for e in err {
-        eprintln!("\t- {}", e.pp(&lexer, &fang_y::token_epp));
+    let mut files = Vec::new();
+    for source in paths {
+        match &source {
+            Source::File(path) if Path::new(path).is_dir() => {
+                let mut entries = fs::read_dir(path)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Unable to read {path}: {e}");
+                        exit(1);
+                    })
+                    .filter_map(Result::ok)
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "fg"))
+                    .map(|p| Source::File(p.to_string_lossy().to_string()))
+                    .collect::<Vec<Source>>();
+                entries.sort_by_key(|s| format!("{s:?}"));
+                files.extend(entries);
+            }
+            _ => files.push(source),
+        }
     }
 
-    match res {
-        Some(Ok(ast)) => {
-            fs::write("./fg.ast", format!("{ast:#?}")).unwrap();
-            eval_bytecode(ast, &mut GLOBAL_SCOPE.clone())
-                .map_err(|e| eprintln!("{}", e.to_string()))
-                .ok()
+    files
+}
+
+/// Runs every top level `test_*` function, each in a fresh copy of the script's scope.
+fn test(cli: &Cli, json: bool) {
+    let (mut passed, mut failed) = (0, 0);
+    for source in test_files(cli) {
+        let (name, src) = read(&source);
+        let mut engine = engine(cli, &name);
+        let ast = parse_or_exit(&engine, &src, json);
+        let tests = ast
+            .iter()
+            .filter_map(|n| match n {
+                Node::Function { name, span, .. } if name.starts_with("test_") => {
+                    Some((name.clone(), span.clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<(String, Spans)>>();
+
+        println!("running {} tests from {name}", tests.len());
+
+        if let Err(e) = engine.run(ast) {
+            report(&e, json);
+            failed += tests.len();
+            continue;
         }
-        _ => {
-            eprintln!("\nFang Failed :(");
-            panic!()
+
+        for (name, span) in tests {
+            let res = engine.scope().clone().call(&name, vec![], &span);
+            match res {
+                Ok(_) => {
+                    println!("test {name} ... ok");
+                    passed += 1;
+                }
+                Err(e) => {
+                    println!("test {name} ... FAILED");
+                    report(&e, json);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!(
+        "\ntest result: {}. {passed} passed; {failed} failed",
+        if failed == 0 { "ok" } else { "FAILED" }
+    );
+    if failed > 0 {
+        exit(1);
+    }
+}
+
+fn main() {
+    let cli = Cli::parse(args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(EXIT_SYNTAX);
+    });
+
+    if cli.has_flag("--help") || cli.has_flag("-h") {
+        println!("{USAGE}");
+        return;
+    }
+
+    let json = match flag::<String>(&cli.flags, "--error-format").as_deref() {
+        None | Some("human") => false,
+        Some("json") => true,
+        Some(format) => {
+            eprintln!("Unknown error format {format}, expected human or json");
+            exit(1);
         }
     };
+
+    match cli.command {
+        Command::Explain => match cli.args.first().map(|code| (code, explain(code))) {
+            Some((_, Some(explanation))) => print!("{explanation}"),
+            Some((code, None)) => {
+                eprintln!("No explanation for {code}");
+                exit(1);
+            }
+            None => {
+                for e in EXPLANATIONS {
+                    println!("{} {}", e.code, e.title);
+                }
+            }
+        },
+        Command::Repl => exit(Repl::new(engine(&cli, "<repl>")).run()),
+        Command::Fmt => fmt(&cli, json),
+        Command::Test => test(&cli, json),
+        Command::Check => {
+            let mut denied = 0;
+            for source in &cli.sources {
+                let (name, src) = read(source);
+                let ast = parse_or_exit(&engine(&cli, &name), &src, json);
+                denied += check(&cli, &src, &ast, json);
+            }
+            if denied > 0 {
+                eprintln!("Aborting due to {denied} denied warning(s)");
+                exit(1);
+            }
+        }
+        Command::Ast => {
+            let (name, src) = read(&cli.sources[0]);
+            let ast = parse_or_exit(&engine(&cli, &name), &src, json);
+            println!("{ast:#?}");
+        }
+        Command::Disasm => {
+            let (name, src) = read(&cli.sources[0]);
+            let ast = parse_or_exit(&engine(&cli, &name), &src, json);
+            print!("{}", disassemble(ast, cli.has_flag("-O")));
+        }
+        Command::Run => {
+            let (name, src) = read(&cli.sources[0]);
+            let mut engine = engine(&cli, &name);
+            let ast = parse_or_exit(&engine, &src, json);
+
+            let denied = check(&cli, &src, &ast, json);
+            if denied > 0 {
+                eprintln!("Aborting due to {denied} denied warning(s)");
+                exit(1);
+            }
+
+            *SCRIPT_ARGS.lock().unwrap() = cli.args.clone();
+            match engine.run(ast) {
+                // A top level `return` of an int sets the exit status, wrapping like `exit`.
+                Ok(Value::Int(val)) => exit((val % 256) as i32),
+                Ok(_) => (),
+                Err(e @ FangErr::Exit { .. }) => exit(e.exit_code()),
+                Err(e) => {
+                    report(&e, json);
+                    exit(e.exit_code());
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,241 @@
+use std::f64::consts;
+
+use crate::{
+    ast::{Node, Spans},
+    errs::FangErr,
+    native::{native, FromFang, IntoFang},
+    value::Value,
+};
+
+/// An `int` or a `float`. Functions taking two keep ints as ints, and otherwise coerce both
+/// to floats, like arithmetic does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Num {
+    Int(u64),
+    Float(f64),
+}
+
+impl Num {
+    fn float(self) -> f64 {
+        match self {
+            Num::Int(val) => val as f64,
+            Num::Float(val) => val,
+        }
+    }
+}
+
+impl FromFang for Num {
+    const TYPE: &'static str = "any";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        match value {
+            Value::Int(val) => Ok(Num::Int(val)),
+            Value::Float(val) => Ok(Num::Float(val)),
+            v => Err(FangErr::TypeMismatch {
+                span: Spans::empty(),
+                expected: "int or float".to_string(),
+                found: v.get_type(),
+                scope: "math".to_string(),
+            }),
+        }
+    }
+}
+
+impl IntoFang for Num {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(match self {
+            Num::Int(val) => Value::Int(val),
+            Num::Float(val) => Value::Float(val),
+        })
+    }
+}
+
+fn pair(a: Num, b: Num, int: fn(u64, u64) -> u64, float: fn(f64, f64) -> f64) -> Num {
+    match (a, b) {
+        (Num::Int(a), Num::Int(b)) => Num::Int(int(a, b)),
+        (a, b) => Num::Float(float(a.float(), b.float())),
+    }
+}
+
+// Ints are already whole, so rounding leaves them be.
+fn whole(x: Num, f: fn(f64) -> f64) -> Num {
+    match x {
+        Num::Int(val) => Num::Int(val),
+        Num::Float(val) => Num::Float(f(val)),
+    }
+}
+
+fn invalid(message: String) -> FangErr {
+    FangErr::InvalidValue {
+        span: Spans::empty(),
+        message,
+    }
+}
+
+fn pow(base: Num, exp: Num) -> Result<Num, FangErr> {
+    match (base, exp) {
+        (Num::Int(a), Num::Int(b)) => u32::try_from(b)
+            .ok()
+            .and_then(|b| a.checked_pow(b))
+            .map(Num::Int)
+            .ok_or(FangErr::IntegerOverflow {
+                span: Spans::empty(),
+                op: "exponentiate".to_string(),
+                lhs: a.to_string(),
+                rhs: b.to_string(),
+                scope: "math.pow".to_string(),
+            }),
+        (a, b) => Ok(Num::Float(a.float().powf(b.float()))),
+    }
+}
+
+fn clamp(x: Num, lo: Num, hi: Num) -> Result<Num, FangErr> {
+    if lo.float() > hi.float() {
+        return Err(invalid(format!(
+            "the lower bound {} is above the upper bound {}",
+            lo.float(),
+            hi.float()
+        )));
+    }
+    Ok(pair(
+        pair(x, lo, u64::max, f64::max),
+        hi,
+        u64::min,
+        f64::min,
+    ))
+}
+
+fn field(name: &str, value: Node) -> Node {
+    Node::Field {
+        name: name.to_string(),
+        value: Box::new(value),
+        span: Spans::empty(),
+    }
+}
+
+fn float_fn(name: &str, f: fn(f64) -> f64) -> Node {
+    field(name, native(name, move |x: Num| f(x.float())))
+}
+
+fn constant(name: &str, val: f64) -> Node {
+    field(
+        name,
+        Node::Float {
+            val,
+            span: Spans::empty(),
+        },
+    )
+}
+
+/// The `math` object.
+pub fn module() -> Node {
+    let fields = vec![
+        constant("PI", consts::PI),
+        constant("E", consts::E),
+        field("abs", native("abs", |x: Num| whole(x, f64::abs))),
+        field(
+            "min",
+            native("min", |a: Num, b: Num| pair(a, b, u64::min, f64::min)),
+        ),
+        field(
+            "max",
+            native("max", |a: Num, b: Num| pair(a, b, u64::max, f64::max)),
+        ),
+        field("clamp", native("clamp", clamp)),
+        field("floor", native("floor", |x: Num| whole(x, f64::floor))),
+        field("ceil", native("ceil", |x: Num| whole(x, f64::ceil))),
+        field("round", native("round", |x: Num| whole(x, f64::round))),
+        field("pow", native("pow", pow)),
+        float_fn("sqrt", f64::sqrt),
+        float_fn("exp", f64::exp),
+        float_fn("ln", f64::ln),
+        float_fn("log10", f64::log10),
+        float_fn("sin", f64::sin),
+        float_fn("cos", f64::cos),
+        float_fn("tan", f64::tan),
+        float_fn("asin", f64::asin),
+        float_fn("acos", f64::acos),
+        float_fn("atan", f64::atan),
+        field(
+            "atan2",
+            native("atan2", |y: Num, x: Num| y.float().atan2(x.float())),
+        ),
+    ];
+
+    Node::Object {
+        typed: "<Internal>".to_string(),
+        fields: Box::new(fields),
+        span: Spans::empty(),
+    }
+}
+
+/// `int(x)`, truncating floats and parsing strings.
+pub fn int() -> Node {
+    native("int", |x: Value| match x {
+        Value::Int(val) => Ok(val),
+        Value::Bool(val) => Ok(val as u64),
+        Value::Float(val) if val.is_finite() && val > -1.0 && val < u64::MAX as f64 => {
+            Ok(val as u64)
+        }
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| invalid(format!("`{s}` isn't an int"))),
+        v => Err(invalid(format!("{v} can't be converted to an int"))),
+    })
+}
+
+/// `float(x)`, widening ints and parsing strings.
+pub fn float() -> Node {
+    native("float", |x: Value| match x {
+        Value::Int(val) => Ok(val as f64),
+        Value::Float(val) => Ok(val),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| invalid(format!("`{s}` isn't a float"))),
+        v => Err(invalid(format!("{v} can't be converted to a float"))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{engine::Engine, errs::FangErr, value::Value};
+
+    #[test]
+    fn keeps_ints_as_ints() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("math.max(3, 7)").unwrap(), Value::Int(7));
+        assert_eq!(engine.eval("math.min(3, 2.5)").unwrap(), Value::Float(2.5));
+        assert_eq!(
+            engine.eval("math.clamp(12, 0, 10)").unwrap(),
+            Value::Int(10)
+        );
+        assert_eq!(engine.eval("math.pow(2, 10)").unwrap(), Value::Int(1024));
+        assert_eq!(engine.eval("math.pow(4, 0.5)").unwrap(), Value::Float(2.0));
+        assert_eq!(engine.eval("math.round(2.5)").unwrap(), Value::Float(3.0));
+        assert_eq!(engine.eval("math.floor(7)").unwrap(), Value::Int(7));
+        assert_eq!(engine.eval("math.sqrt(16)").unwrap(), Value::Float(4.0));
+        assert!(matches!(
+            engine.eval("math.pow(2, 64)").unwrap_err().kind(),
+            FangErr::IntegerOverflow { .. }
+        ));
+        assert!(matches!(
+            engine.eval("math.abs(\"1\")").unwrap_err().kind(),
+            FangErr::TypeMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn conversions() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("int(3.9)").unwrap(), Value::Int(3));
+        assert_eq!(engine.eval("int(\"42\")").unwrap(), Value::Int(42));
+        assert_eq!(engine.eval("float(2)").unwrap(), Value::Float(2.0));
+        assert_eq!(
+            engine.eval("math.PI * 2").unwrap(),
+            Value::Float(std::f64::consts::TAU)
+        );
+        assert!(engine.eval("int(\"4x\")").is_err());
+    }
+}
@@ -0,0 +1,719 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::{
+    ast::{BuiltinFnBody, NativeHandle, Node, Spans, LIST},
+    errs::FangErr,
+    scope::Scope,
+    value::Value,
+};
+
+/// A Rust type that can be taken from a Fang value, used for the arguments of native functions.
+pub trait FromFang: Sized {
+    /// The Fang type the value must have, `any` to accept every value.
+    const TYPE: &'static str;
+
+    fn from_fang(value: Value) -> Result<Self, FangErr>;
+}
+
+/// A Rust type that can be handed to Fang, used for what native functions return.
+pub trait IntoFang {
+    fn into_fang(self) -> Result<Value, FangErr>;
+}
+
+fn failed(message: String) -> FangErr {
+    FangErr::Native {
+        span: Spans::empty(),
+        message,
+    }
+}
+
+fn mismatch(expected: &str, found: &Value) -> FangErr {
+    FangErr::TypeMismatch {
+        span: Spans::empty(),
+        expected: expected.to_string(),
+        found: found.get_type(),
+        scope: "<native>".to_string(),
+    }
+}
+
+impl FromFang for Value {
+    const TYPE: &'static str = "any";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        Ok(value)
+    }
+}
+
+impl IntoFang for Value {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(self)
+    }
+}
+
+macro_rules! int_conversions {
+    ($($t:ty),*) => {
+        $(
+            impl FromFang for $t {
+                const TYPE: &'static str = "int";
+
+                fn from_fang(value: Value) -> Result<Self, FangErr> {
+                    match value {
+                        Value::Int(val) => <$t>::try_from(val).map_err(|_| {
+                            failed(format!("{val} doesn't fit in {}", stringify!($t)))
+                        }),
+                        v => Err(mismatch(Self::TYPE, &v)),
+                    }
+                }
+            }
+
+            impl IntoFang for $t {
+                fn into_fang(self) -> Result<Value, FangErr> {
+                    u64::try_from(self)
+                        .map(Value::Int)
+                        .map_err(|_| failed(format!("{self} can't be represented as an int")))
+                }
+            }
+        )*
+    };
+}
+
+int_conversions!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl FromFang for f64 {
+    const TYPE: &'static str = "float";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        match value {
+            Value::Float(val) => Ok(val),
+            v => Err(mismatch(Self::TYPE, &v)),
+        }
+    }
+}
+
+impl IntoFang for f64 {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::Float(self))
+    }
+}
+
+impl FromFang for f32 {
+    const TYPE: &'static str = "float";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        f64::from_fang(value).map(|v| v as f32)
+    }
+}
+
+impl IntoFang for f32 {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::Float(self as f64))
+    }
+}
+
+impl FromFang for bool {
+    const TYPE: &'static str = "bool";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        match value {
+            Value::Bool(val) => Ok(val),
+            v => Err(mismatch(Self::TYPE, &v)),
+        }
+    }
+}
+
+impl IntoFang for bool {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::Bool(self))
+    }
+}
+
+impl FromFang for String {
+    const TYPE: &'static str = "string";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        match value {
+            Value::String(val) => Ok(val),
+            v => Err(mismatch(Self::TYPE, &v)),
+        }
+    }
+}
+
+impl IntoFang for String {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::String(self))
+    }
+}
+
+impl IntoFang for &str {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::String(self.to_string()))
+    }
+}
+
+impl IntoFang for () {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::None)
+    }
+}
+
+impl FromFang for () {
+    const TYPE: &'static str = "any";
+
+    fn from_fang(_: Value) -> Result<Self, FangErr> {
+        Ok(())
+    }
+}
+
+impl<T: IntoFang> IntoFang for Option<T> {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        self.map_or(Ok(Value::None), T::into_fang)
+    }
+}
+
+/// Taken from a list.
+impl<T: FromFang> FromFang for Vec<T> {
+    // Lists are objects, whose type names their fields, so only `any` matches them.
+    const TYPE: &'static str = "any";
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        match value {
+            Value::Object { typed, fields } if typed == LIST => {
+                fields.into_iter().map(|(_, v)| T::from_fang(v)).collect()
+            }
+            v => Err(mismatch(LIST, &v)),
+        }
+    }
+}
+
+/// Converted to a list.
+impl<T: IntoFang> IntoFang for Vec<T> {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::Object {
+            typed: LIST.to_string(),
+            fields: self
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| Ok((i.to_string(), v.into_fang()?)))
+                .collect::<Result<_, FangErr>>()?,
+        })
+    }
+}
+
+/// Errors are raised in the calling script.
+impl<T: IntoFang> IntoFang for Result<T, FangErr> {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        self.and_then(T::into_fang)
+    }
+}
+
+/// Errors are raised in the calling script as `FangErr::Native`.
+impl<T: IntoFang> IntoFang for Result<T, String> {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        self.map_err(failed).and_then(T::into_fang)
+    }
+}
+
+impl<T: IntoFang> IntoFang for Result<T, &str> {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        self.map_err(|e| failed(e.to_string()))
+            .and_then(T::into_fang)
+    }
+}
+
+/// Arguments for calling a Fang function from Rust, implemented for tuples of up to six values
+/// that implement `IntoFang`.
+pub trait IntoArgs {
+    fn into_args(self) -> Result<Vec<Value>, FangErr>;
+}
+
+impl IntoArgs for Vec<Value> {
+    fn into_args(self) -> Result<Vec<Value>, FangErr> {
+        Ok(self)
+    }
+}
+
+macro_rules! into_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: IntoFang),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Result<Vec<Value>, FangErr> {
+                let ($($arg,)*) = self;
+                Ok(vec![$($arg.into_fang()?),*])
+            }
+        }
+    };
+}
+
+into_args!();
+into_args!(A);
+into_args!(A, B);
+into_args!(A, B, C);
+into_args!(A, B, C, D);
+into_args!(A, B, C, D, E);
+into_args!(A, B, C, D, E, G);
+
+/// A Rust function callable from Fang, implemented for closures of up to six arguments that
+/// all implement `FromFang` and return something implementing `IntoFang`.
+pub trait NativeFn<Args>: 'static {
+    fn arg_types() -> Vec<&'static str>;
+
+    fn call(&self, args: Vec<Value>) -> Result<Value, FangErr>;
+}
+
+macro_rules! native_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> NativeFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoFang,
+            $($arg: FromFang),*
+        {
+            fn arg_types() -> Vec<&'static str> {
+                vec![$($arg::TYPE),*]
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, args: Vec<Value>) -> Result<Value, FangErr> {
+                let mut args = args.into_iter();
+                $(let $arg = $arg::from_fang(args.next().unwrap_or(Value::None))?;)*
+                self($($arg),*).into_fang()
+            }
+        }
+    };
+}
+
+native_fn!();
+native_fn!(A);
+native_fn!(A, B);
+native_fn!(A, B, C);
+native_fn!(A, B, C, D);
+native_fn!(A, B, C, D, E);
+native_fn!(A, B, C, D, E, G);
+
+// A builtin taking `types`, its parameters named `arg0`, `arg1`, ...
+fn builtin<F>(name: &str, types: Vec<&'static str>, f: F) -> Node
+where
+    F: Fn(Vec<Value>) -> Result<Value, FangErr> + 'static,
+{
+    let names = (0..types.len())
+        .map(|i| format!("arg{i}"))
+        .collect::<Vec<String>>();
+    let args = types
+        .into_iter()
+        .zip(&names)
+        .map(|(var_type, name)| Node::TypedVariable {
+            var_type: var_type.to_string(),
+            name: name.clone(),
+            span: Spans::empty(),
+        })
+        .collect();
+
+    let body = move |scope: &Scope| {
+        let args = names
+            .iter()
+            .map(|n| scope.get(n).map_or(Value::None, Value::from_node))
+            .collect::<Vec<Value>>();
+        match f(args)? {
+            Value::None => Ok(None),
+            v => Ok(Some(v.into_node())),
+        }
+    };
+
+    Node::BuiltinFn {
+        name: name.to_string(),
+        args: Box::new(args),
+        body: BuiltinFnBody(Rc::new(body)),
+        return_type: None,
+        span: Spans::empty(),
+    }
+}
+
+/// Wraps `f` as a builtin function.
+pub fn native<Args, F: NativeFn<Args>>(name: &str, f: F) -> Node {
+    builtin(name, F::arg_types(), move |args| f.call(args))
+}
+
+/// A Rust type that scripts can hold, registered with `Engine::register_type`.
+///
+/// Values of the type are converted to and from Fang as shared handles, so a script and the
+/// host see the same value. Taking one as an argument clones it out of the handle.
+pub trait FangType: Any {
+    const NAME: &'static str;
+}
+
+impl<T: FangType> IntoFang for T {
+    fn into_fang(self) -> Result<Value, FangErr> {
+        Ok(Value::Native {
+            typed: T::NAME.to_string(),
+            handle: NativeHandle(Rc::new(RefCell::new(self))),
+        })
+    }
+}
+
+impl<T: FangType + Clone> FromFang for T {
+    const TYPE: &'static str = T::NAME;
+
+    fn from_fang(value: Value) -> Result<Self, FangErr> {
+        match &value {
+            Value::Native { handle, .. } => handle
+                .0
+                .borrow()
+                .downcast_ref::<T>()
+                .cloned()
+                .ok_or_else(|| mismatch(T::NAME, &value)),
+            v => Err(mismatch(T::NAME, v)),
+        }
+    }
+}
+
+/// A method of `T` callable from Fang, implemented for closures taking `&mut T` followed by up
+/// to five arguments that implement `FromFang`.
+pub trait NativeMethod<T, Args>: 'static {
+    fn arg_types() -> Vec<&'static str>;
+
+    /// Calls the method on the `T` in `this`. The arguments are converted before `this` is
+    /// borrowed, so a value can be passed to its own method.
+    fn call(&self, this: &NativeHandle, args: Vec<Value>) -> Result<Value, FangErr>;
+}
+
+macro_rules! native_method {
+    ($($arg:ident),*) => {
+        impl<T: 'static, F, R, $($arg),*> NativeMethod<T, ($($arg,)*)> for F
+        where
+            F: Fn(&mut T, $($arg),*) -> R + 'static,
+            R: IntoFang,
+            $($arg: FromFang),*
+        {
+            fn arg_types() -> Vec<&'static str> {
+                vec![$($arg::TYPE),*]
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, this: &NativeHandle, args: Vec<Value>) -> Result<Value, FangErr> {
+                let mut args = args.into_iter();
+                $(let $arg = $arg::from_fang(args.next().unwrap_or(Value::None))?;)*
+                let mut this = this.0.borrow_mut();
+                self(this.downcast_mut::<T>().unwrap(), $($arg),*).into_fang()
+            }
+        }
+    };
+}
+
+native_method!();
+native_method!(A);
+native_method!(A, B);
+native_method!(A, B, C);
+native_method!(A, B, C, D);
+native_method!(A, B, C, D, E);
+
+type Getter = Rc<dyn Fn(&dyn Any) -> Result<Value, FangErr>>;
+type Setter = Rc<dyn Fn(&mut dyn Any, Value) -> Result<(), FangErr>>;
+type Method = Rc<dyn Fn(&NativeHandle, Vec<Value>) -> Result<Value, FangErr>>;
+
+/// The fields and methods of a registered Rust type, with the type itself erased.
+#[derive(Default)]
+pub struct NativeClass {
+    getters: HashMap<String, Getter>,
+    setters: HashMap<String, (&'static str, Setter)>,
+    methods: HashMap<String, (Vec<&'static str>, Method)>,
+}
+
+impl Debug for NativeClass {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("NativeClass")
+            .field("fields", &self.getters.keys().collect::<Vec<_>>())
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl NativeClass {
+    /// Reads the field `name` of `handle`, or binds the method of that name to it.
+    pub fn get(&self, handle: &NativeHandle, name: &str) -> Option<Node> {
+        if let Some(get) = self.getters.get(name) {
+            return get(&*handle.0.borrow()).ok().map(Value::into_node);
+        }
+
+        let (types, method) = self.methods.get(name)?;
+        let (handle, method) = (handle.clone(), method.clone());
+        Some(builtin(name, types.clone(), move |args| {
+            method(&handle, args)
+        }))
+    }
+
+    pub fn set(
+        &self,
+        handle: &NativeHandle,
+        name: &str,
+        val: Node,
+        span: &Spans,
+        scope: &str,
+    ) -> Result<(), FangErr> {
+        let Some((var_type, set)) = self.setters.get(name) else {
+            return Err(FangErr::UndeclaredVariable {
+                span: span.clone(),
+                name: name.to_string(),
+                scope: scope.to_string(),
+            });
+        };
+
+        let value = Value::from_node(val);
+        if *var_type != "any" && value.get_type() != *var_type {
+            return Err(FangErr::TypeMismatch {
+                span: span.clone(),
+                expected: var_type.to_string(),
+                found: value.get_type(),
+                scope: scope.to_string(),
+            });
+        }
+
+        set(&mut *handle.0.borrow_mut(), value)
+    }
+}
+
+/// Describes how scripts can use the Rust type `T`, see `Engine::register_type`.
+///
+/// ```
+/// use fang::{native::{FangType, NativeType}, Engine, Value};
+///
+/// #[derive(Clone)]
+/// struct Counter {
+///     count: u64,
+/// }
+///
+/// impl FangType for Counter {
+///     const NAME: &'static str = "Counter";
+/// }
+///
+/// let mut engine = Engine::new();
+/// engine.register_type(
+///     NativeType::<Counter>::new()
+///         .constructor(|count: u64| Counter { count })
+///         .field("count", |c: &Counter| c.count, |c: &mut Counter, v: u64| c.count = v)
+///         .method("add", |c: &mut Counter, n: u64| {
+///             c.count += n;
+///             c.count
+///         }),
+/// );
+///
+/// engine.eval("let c = Counter.new(1); c.add(2); c.count = c.count * 2;").unwrap();
+/// assert_eq!(engine.eval("c.count").unwrap(), Value::Int(6));
+/// ```
+pub struct NativeType<T> {
+    pub(crate) class: NativeClass,
+    pub(crate) constructor: Option<Node>,
+    marker: PhantomData<T>,
+}
+
+impl<T: FangType> Default for NativeType<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FangType> NativeType<T> {
+    pub fn new() -> Self {
+        Self {
+            class: NativeClass::default(),
+            constructor: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Lets scripts create values with `Name.new(...)`, `f` should return a `T`.
+    pub fn constructor<Args, F: NativeFn<Args>>(mut self, f: F) -> Self {
+        self.constructor = Some(native("new", f));
+        self
+    }
+
+    /// A field scripts can read, values that can't be converted read as undeclared.
+    pub fn getter<R, G>(mut self, name: &str, get: G) -> Self
+    where
+        R: IntoFang,
+        G: Fn(&T) -> R + 'static,
+    {
+        let get = move |this: &dyn Any| get(this.downcast_ref::<T>().unwrap()).into_fang();
+        self.class.getters.insert(name.to_string(), Rc::new(get));
+        self
+    }
+
+    /// A field scripts can read and assign to.
+    pub fn field<R, V, G, S>(self, name: &str, get: G, set: S) -> Self
+    where
+        R: IntoFang,
+        V: FromFang,
+        G: Fn(&T) -> R + 'static,
+        S: Fn(&mut T, V) + 'static,
+    {
+        let mut this = self.getter(name, get);
+        let set = move |this: &mut dyn Any, value: Value| {
+            set(this.downcast_mut::<T>().unwrap(), V::from_fang(value)?);
+            Ok(())
+        };
+        this.class
+            .setters
+            .insert(name.to_string(), (V::TYPE, Rc::new(set)));
+        this
+    }
+
+    pub fn method<Args, F: NativeMethod<T, Args>>(mut self, name: &str, f: F) -> Self {
+        let method = move |this: &NativeHandle, args: Vec<Value>| f.call(this, args);
+        self.class
+            .methods
+            .insert(name.to_string(), (F::arg_types(), Rc::new(method)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    #[test]
+    fn converts_arguments_and_results() {
+        let mut engine = Engine::new();
+        engine.register_fn("add", |a: i64, b: i64| a + b);
+        engine.register_fn("shout", |s: String| s.to_uppercase());
+        engine.register_fn("answer", || 42u8);
+
+        assert_eq!(engine.eval("add(2, 3)").unwrap(), Value::Int(5));
+        assert_eq!(
+            engine.eval("shout(\"hi\")").unwrap(),
+            Value::String("HI".to_string())
+        );
+        assert_eq!(engine.eval("answer() + 1").unwrap(), Value::Int(43));
+    }
+
+    #[test]
+    fn checks_arity_and_types() {
+        let mut engine = Engine::new();
+        engine.register_fn("add", |a: i64, b: i64| a + b);
+
+        let err = engine.eval("add(1)").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::ArgumentLengthMismatch { .. }));
+        let err = engine.eval("add(1, \"2\")").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn errors_become_fang_errors() {
+        let mut engine = Engine::new();
+        engine.register_fn("half", |x: u64| match x % 2 {
+            0 => Ok(x / 2),
+            _ => Err(format!("{x} is odd")),
+        });
+        engine.register_fn("negate", |x: i64| -x);
+
+        assert_eq!(engine.eval("half(4)").unwrap(), Value::Int(2));
+        let err = engine.eval("half(3)").unwrap_err();
+        assert_eq!(err.code(), "F0022");
+        assert_eq!(err.message(), "3 is odd");
+        assert!(engine.eval("negate(1)").is_err());
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Point {
+        x: u64,
+        y: u64,
+    }
+
+    impl FangType for Point {
+        const NAME: &'static str = "Point";
+    }
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.register_type(
+            NativeType::<Point>::new()
+                .constructor(|x: u64, y: u64| Point { x, y })
+                .field("x", |p: &Point| p.x, |p: &mut Point, x: u64| p.x = x)
+                .getter("y", |p: &Point| p.y)
+                .method("shift", |p: &mut Point, by: u64| {
+                    p.x += by;
+                    p.y += by;
+                })
+                .method("merge", |p: &mut Point, other: Point| {
+                    p.x += other.x;
+                    p.y += other.y;
+                }),
+        );
+        engine
+    }
+
+    #[test]
+    fn scripts_use_rust_types() {
+        let mut engine = engine();
+        engine
+            .eval("let p = Point.new(1, 2);\np.shift(3);\np.x = p.x + p.y;")
+            .unwrap();
+
+        assert_eq!(engine.eval("p.x").unwrap(), Value::Int(9));
+        let p = Point::from_fang(engine.get_global("p").unwrap()).unwrap();
+        assert_eq!(p, Point { x: 9, y: 5 });
+    }
+
+    #[test]
+    fn values_are_passed_to_their_own_methods() {
+        let mut engine = engine();
+        engine
+            .eval("let p = Point.new(1, 2);\np.merge(p);")
+            .unwrap();
+        assert_eq!(engine.eval("p.x").unwrap(), Value::Int(2));
+        assert_eq!(engine.eval("p.y").unwrap(), Value::Int(4));
+    }
+
+    #[test]
+    fn handles_are_shared() {
+        let mut engine = engine();
+        engine.set_global("p", Point { x: 1, y: 1 }.into_fang().unwrap());
+        engine.eval("let q = p;\nq.x = 7;").unwrap();
+        assert_eq!(engine.eval("p.x").unwrap(), Value::Int(7));
+    }
+
+    #[test]
+    fn checks_fields() {
+        let mut engine = engine();
+        engine.eval("let p = Point.new(1, 2);").unwrap();
+
+        let err = engine.eval("p.y = 3;").unwrap_err();
+        assert!(matches!(err, FangErr::UndeclaredVariable { .. }));
+        let err = engine.eval("p.x = \"3\";").unwrap_err();
+        assert!(matches!(err, FangErr::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn rust_types_implement_fang_traits() {
+        let mut engine = engine();
+        engine
+            .eval(
+                "trait Shape {\n\
+                 fn name(self): string;\n\
+                 fn describe(self, unit: string): string { return \"a shape in \" + unit; }\n\
+                 };\n\
+                 impl Shape for Point { fn name(self): string { return \"point\"; } };\n\
+                 let p = Point.new(1, 2);",
+            )
+            .unwrap();
+
+        assert_eq!(
+            engine.eval("p.name()").unwrap(),
+            Value::String("point".to_string())
+        );
+        assert_eq!(
+            engine.eval("p.describe(\"cm\")").unwrap(),
+            Value::String("a shape in cm".to_string())
+        );
+        let err = engine
+            .eval("impl Shape for Point { fn name(self): string { return \"p\"; } };")
+            .unwrap_err();
+        assert!(matches!(err, FangErr::AlreadyImplementedTrait { .. }));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    ast::Node,
+    bytecode::{arithmetic, Op},
+    scope::Scope,
+};
+
+pub fn optimise(ops: Vec<Op>) -> Vec<Op> {
+    eliminate_dead_code(fold_constants(ops))
+}
+
+fn is_constant(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::Integer { .. } | Node::Float { .. } | Node::String { .. } | Node::Boolean { .. }
+    )
+}
+
+fn is_arithmetic(op: &Op) -> bool {
+    matches!(
+        op,
+        Op::Add { .. }
+            | Op::Subtract { .. }
+            | Op::Multiply { .. }
+            | Op::Divide { .. }
+            | Op::Modulo { .. }
+    )
+}
+
+// Operands are pushed rhs first, so the lhs is the top of the stack. Anything that
+// fails to fold (e.g. `1 + true`) is left for the VM to report at runtime.
+fn fold_constants(ops: Vec<Op>) -> Vec<Op> {
+    let scope = Scope::new("<Optimiser>".to_string(), None);
+    let mut folded = Vec::<Op>::with_capacity(ops.len());
+
+    for op in ops {
+        if is_arithmetic(&op) {
+            if let [.., Op::Push { value: rhs }, Op::Push { value: lhs }] = folded.as_slice() {
+                if is_constant(lhs) && is_constant(rhs) {
+                    if let Ok(value) = arithmetic(&op, lhs.clone(), rhs.clone(), &scope) {
+                        folded.truncate(folded.len() - 2);
+                        folded.push(Op::Push { value });
+                        continue;
+                    }
+                }
+            }
+        }
+
+        folded.push(op);
+    }
+
+    folded
+}
+
+// There are no jump targets in the op stream, so nothing after a `Return` or `TailCall` can run.
+fn eliminate_dead_code(mut ops: Vec<Op>) -> Vec<Op> {
+    if let Some(i) = ops
+        .iter()
+        .position(|op| matches!(op, Op::Return | Op::TailCall { .. }))
+    {
+        ops.truncate(i + 1);
+    }
+
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{ast::Spans, bytecode::eval_bytecode, scope::global_scope, testing::parse};
+
+    fn run(src: &str, optimise: bool) -> (Option<String>, BTreeMap<String, String>) {
+        let mut scope = global_scope();
+        scope.optimise = optimise;
+
+        let ret = eval_bytecode(parse(src), &mut scope).unwrap();
+        let store = scope
+            .store
+            .iter()
+            .filter(|(name, _)| *name != "console")
+            .map(|(name, val)| {
+                (
+                    name.clone(),
+                    format!("{}: {}", val.get_type(), val.inspect()),
+                )
+            })
+            .collect();
+
+        (ret.map(|n| n.inspect()), store)
+    }
+
+    fn assert_same(src: &str) {
+        assert_eq!(run(src, false), run(src, true), "{src}");
+    }
+
+    fn int(val: u64) -> Op {
+        Op::Push {
+            value: Node::Integer {
+                val,
+                span: Spans::empty(),
+            },
+        }
+    }
+
+    #[test]
+    fn folds_nested_arithmetic() {
+        let ops = vec![
+            int(3),
+            int(2),
+            Op::Multiply {
+                span: Spans::empty(),
+            },
+            int(1),
+            Op::Add {
+                span: Spans::empty(),
+            },
+        ];
+
+        assert_eq!(optimise(ops), vec![int(7)]);
+    }
+
+    #[test]
+    fn leaves_loads_unfolded() {
+        let ops = vec![
+            int(2),
+            Op::Load {
+                name: "a".to_string(),
+                span: Spans::empty(),
+            },
+            Op::Add {
+                span: Spans::empty(),
+            },
+        ];
+
+        assert_eq!(optimise(ops.clone()), ops);
+    }
+
+    #[test]
+    fn leaves_errors_for_runtime() {
+        let ops = vec![
+            int(0),
+            int(1),
+            Op::Divide {
+                span: Spans::empty(),
+            },
+        ];
+
+        assert_eq!(optimise(ops.clone()), ops);
+    }
+
+    #[test]
+    fn drops_ops_after_return() {
+        let ops = vec![int(1), Op::Return, int(2), Op::Return];
+        assert_eq!(optimise(ops), vec![int(1), Op::Return]);
+    }
+
+    #[test]
+    fn identical_arithmetic() {
+        assert_same("let a = 1 + 2 * 3; let b = 10 / 4; let c = 10.0 / 4; let d = 7 - 2 - 1;");
+        assert_same("let a = 1.5 * 2 + 3; let b = a * 2;");
+    }
+
+    #[test]
+    fn identical_string_coercion() {
+        assert_same(r#"let a = "n = " + 4 * 2; let b = 1.5 + "x" + true;"#);
+    }
+
+    #[test]
+    fn identical_function_calls() {
+        assert_same(
+            "fn f(x: int): int { return x * (2 + 3); let dead = 1; } let a = f(4) + 1 * 2;",
+        );
+    }
+}
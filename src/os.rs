@@ -0,0 +1,261 @@
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sysinfo::System;
+
+use crate::{
+    ast::{Node, Spans},
+    errs::FangErr,
+    native::native,
+    sandbox::{gate, Capability},
+    value::Value,
+    SCRIPT_ARGS,
+};
+
+fn field(name: &str, value: Node) -> Node {
+    Node::Field {
+        name: name.to_string(),
+        value: Box::new(value),
+        span: Spans::empty(),
+    }
+}
+
+fn object(fields: Vec<Node>) -> Node {
+    Node::Object {
+        typed: "<Internal>".to_string(),
+        fields: Box::new(fields),
+        span: Spans::empty(),
+    }
+}
+
+fn io(path: &str, e: std::io::Error) -> FangErr {
+    FangErr::Io {
+        span: Spans::empty(),
+        path: path.to_string(),
+        message: e.to_string(),
+    }
+}
+
+fn get_env(name: String) -> Result<String, FangErr> {
+    env::var(&name).map_err(|e| FangErr::InvalidValue {
+        span: Spans::empty(),
+        message: format!(
+            "environment variable {name} {}",
+            match e {
+                env::VarError::NotPresent => "isn't set",
+                env::VarError::NotUnicode(_) => "isn't valid UTF-8",
+            }
+        ),
+    })
+}
+
+fn sysinfo() -> Value {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.refresh_cpu();
+
+    let pid = sysinfo::get_current_pid().ok();
+    let memory = pid
+        .filter(|pid| sys.refresh_process(*pid))
+        .and_then(|pid| sys.process(pid))
+        .map_or(0, |p| p.memory());
+
+    let string = |s: Option<String>| Value::String(s.unwrap_or_default());
+    Value::Object {
+        typed: "SysInfo".to_string(),
+        fields: vec![
+            ("os".to_string(), string(System::name())),
+            ("os_version".to_string(), string(System::os_version())),
+            ("host_name".to_string(), string(System::host_name())),
+            ("cpus".to_string(), Value::Int(sys.cpus().len() as u64)),
+            ("total_memory".to_string(), Value::Int(sys.total_memory())),
+            ("used_memory".to_string(), Value::Int(sys.used_memory())),
+            (
+                "available_memory".to_string(),
+                Value::Int(sys.available_memory()),
+            ),
+            ("uptime".to_string(), Value::Int(System::uptime())),
+            (
+                "pid".to_string(),
+                Value::Int(pid.map_or(0, |p| p.as_u32() as u64)),
+            ),
+            ("memory".to_string(), Value::Int(memory)),
+        ],
+    }
+}
+
+// A process killed by a signal reports 128 plus the signal, as a shell would.
+fn exit_code(status: std::process::ExitStatus) -> u64 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal as u64;
+    }
+    status.code().map_or(1, |c| c as u32 as u64)
+}
+
+fn run(cmd: String, args: Vec<String>) -> Result<Value, FangErr> {
+    let output = Command::new(&cmd)
+        .args(&args)
+        .output()
+        .map_err(|e| io(&cmd, e))?;
+
+    Ok(Value::Object {
+        typed: "Output".to_string(),
+        fields: vec![
+            ("code".to_string(), Value::Int(exit_code(output.status))),
+            (
+                "stdout".to_string(),
+                Value::String(String::from_utf8_lossy(&output.stdout).into_owned()),
+            ),
+            (
+                "stderr".to_string(),
+                Value::String(String::from_utf8_lossy(&output.stderr).into_owned()),
+            ),
+        ],
+    })
+}
+
+/// The `os` object. Environment variables need the `env` capability, the time needs `clock`,
+/// and everything about processes, including this one, needs `process`.
+pub fn module() -> Node {
+    object(vec![
+        field(
+            "args",
+            native("args", || SCRIPT_ARGS.lock().unwrap().clone()),
+        ),
+        field(
+            "env",
+            gate(
+                Capability::Env,
+                object(vec![
+                    field("get", native("get", get_env)),
+                    field(
+                        "has",
+                        native("has", |name: String| env::var_os(name).is_some()),
+                    ),
+                    field(
+                        "set",
+                        native("set", |name: String, value: String| {
+                            env::set_var(name, value)
+                        }),
+                    ),
+                ]),
+            ),
+        ),
+        field(
+            "time",
+            gate(
+                Capability::Clock,
+                native("time", || {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0.0, |d| d.as_secs_f64())
+                }),
+            ),
+        ),
+        field(
+            "cwd",
+            gate(
+                Capability::Process,
+                native("cwd", || {
+                    env::current_dir()
+                        .map(|dir| dir.display().to_string())
+                        .map_err(|e| io(".", e))
+                }),
+            ),
+        ),
+        field(
+            "sysinfo",
+            gate(Capability::Process, native("sysinfo", sysinfo)),
+        ),
+        field(
+            "process",
+            gate(
+                Capability::Process,
+                object(vec![field("run", native("run", run))]),
+            ),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::Engine,
+        errs::FangErr,
+        sandbox::{Capability, Profile},
+        value::Value,
+    };
+
+    #[test]
+    fn environment_variables() {
+        let mut engine = Engine::new();
+        engine
+            .eval("os.env.set(\"FANG_OS_TEST\", \"on\");")
+            .unwrap();
+        assert_eq!(
+            engine.eval("os.env.get(\"FANG_OS_TEST\")").unwrap(),
+            Value::String("on".to_string())
+        );
+        assert_eq!(
+            engine.eval("os.env.has(\"FANG_OS_UNSET\")").unwrap(),
+            Value::Bool(false)
+        );
+        assert!(matches!(
+            engine
+                .eval("os.env.get(\"FANG_OS_UNSET\")")
+                .unwrap_err()
+                .kind(),
+            FangErr::InvalidValue { .. }
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_processes() {
+        let mut engine = Engine::new();
+        engine
+            .eval("let res = os.process.run(\"sh\", list(\"-c\", \"echo out; echo err >&2; exit 3\"));")
+            .unwrap();
+        assert_eq!(engine.eval("res.code").unwrap(), Value::Int(3));
+        assert_eq!(
+            engine.eval("res.stdout").unwrap(),
+            Value::String("out\n".to_string())
+        );
+        assert_eq!(
+            engine.eval("res.stderr").unwrap(),
+            Value::String("err\n".to_string())
+        );
+    }
+
+    #[test]
+    fn sandboxed_scripts_cannot_reach_the_system() {
+        let mut engine = Engine::new().with_profile(Profile::untrusted());
+        for src in [
+            "os.cwd()",
+            "os.env.has(\"HOME\")",
+            "os.sysinfo()",
+            "os.time()",
+        ] {
+            let err = engine.eval(src).unwrap_err();
+            assert!(
+                matches!(err.kind(), FangErr::PermissionDenied { .. }),
+                "{src}"
+            );
+        }
+        engine.eval("let args = os.args();").unwrap();
+    }
+
+    #[test]
+    fn reads_the_clock() {
+        let mut engine = Engine::new();
+        assert!(matches!(engine.eval("os.time()").unwrap(), Value::Float(t) if t > 1e9));
+
+        let mut engine = Engine::new().with_profile(Profile::trusted().deny(Capability::Clock));
+        let err = engine.eval("os.time()").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::PermissionDenied { .. }));
+    }
+}
@@ -0,0 +1,203 @@
+use cfgrammar::TIdx;
+use lrlex::{DefaultLexeme, DefaultLexerTypes};
+use lrpar::{LexError, LexParseError, Lexeme, NonStreamingLexer, ParseRepair, Span};
+
+use crate::{
+    ast::{Node, Spans},
+    errs::FangErr,
+    fang_l, fang_y,
+};
+
+type Lexer<'a, 'input> = dyn NonStreamingLexer<'input, DefaultLexerTypes> + 'a;
+type Repair = ParseRepair<DefaultLexeme, u32>;
+
+// Only the first few of lrpar's repair sequences are worth showing.
+const MAX_HINTS: usize = 3;
+
+/// Parses `src` into an AST, collecting every syntax error found while recovering.
+pub fn parse(src: &str) -> Result<Vec<Node>, Vec<FangErr>> {
+    let def = fang_l::lexerdef();
+    let lexer = def.lexer(src);
+    let (res, errs) = fang_y::parse(&lexer);
+
+    if !errs.is_empty() {
+        return Err(errs
+            .iter()
+            .map(|e| match e {
+                LexParseError::LexError(e) => lex_error(&lexer, src, e.span()),
+                LexParseError::ParseError(e) => parse_error(&lexer, e.lexeme(), e.repairs()),
+            })
+            .collect());
+    }
+
+    match res {
+        Some(Ok(ast)) => Ok(ast),
+        Some(Err(e)) => Err(vec![e]),
+        None => Err(vec![FangErr::SyntaxError {
+            span: Spans::empty(),
+            message: "unable to parse".to_string(),
+            hints: vec![],
+        }]),
+    }
+}
+
+fn spans(lexer: &Lexer, span: Span) -> Spans {
+    Spans::new(lexer.span_lines_str(span), lexer.line_col(span))
+}
+
+fn token(tidx: TIdx<u32>) -> &'static str {
+    fang_y::token_epp(tidx).unwrap_or("token")
+}
+
+fn found(lexer: &Lexer, lexeme: &DefaultLexeme) -> String {
+    match lexer.span_str(lexeme.span()) {
+        "" => "end of file".to_string(),
+        s => format!("`{s}`"),
+    }
+}
+
+// lrlex reports an empty span at the first character it couldn't match.
+fn lex_error(lexer: &Lexer, src: &str, span: Span) -> FangErr {
+    let c = src[span.start()..].chars().next().unwrap_or(' ');
+    FangErr::SyntaxError {
+        span: spans(lexer, Span::new(span.start(), span.start() + c.len_utf8())),
+        message: format!("unexpected character `{c}`"),
+        hints: vec![],
+    }
+}
+
+// Missing tokens are reported right after the lexeme they should follow, rather than at
+// whatever comes next (which may be several lines further down).
+fn insertion_point(lexer: &Lexer, lexeme: &DefaultLexeme) -> Span {
+    let start = lexeme.span().start();
+    lexer
+        .iter()
+        .filter_map(Result::ok)
+        .take_while(|l| l.span().end() <= start)
+        .last()
+        .map(|l| Span::new(l.span().end(), l.span().end()))
+        .unwrap_or_else(|| lexeme.span())
+}
+
+fn edits(repairs: &[Repair]) -> impl Iterator<Item = &Repair> {
+    repairs
+        .iter()
+        .filter(|r| !matches!(r, ParseRepair::Shift(_)))
+}
+
+// lrpar returns equally good repairs in no particular order, so rank them by the number of
+// edits, preferring a missing `;` as it's by far the most common mistake.
+fn rank(repairs: &[Repair]) -> (usize, bool) {
+    let semicolon =
+        matches!(edits(repairs).next(), Some(ParseRepair::Insert(t)) if token(*t) == ";");
+    (edits(repairs).count(), !semicolon)
+}
+
+fn parse_error(lexer: &Lexer, lexeme: &DefaultLexeme, repairs: &[Vec<Repair>]) -> FangErr {
+    let mut repairs = repairs.iter().collect::<Vec<&Vec<Repair>>>();
+    repairs.sort_by_key(|r| rank(r));
+
+    let found = found(lexer, lexeme);
+    let first = repairs.iter().find_map(|r| edits(r).next());
+
+    let (message, span) = match first {
+        Some(ParseRepair::Insert(tidx)) => {
+            let message = match token(*tidx) {
+                ";" => format!("expected `;` after expression, found {found}"),
+                expected => format!("expected `{expected}`, found {found}"),
+            };
+            (message, insertion_point(lexer, lexeme))
+        }
+        _ => (format!("unexpected {found}"), lexeme.span()),
+    };
+
+    let mut hints = Vec::new();
+    for hint in repairs.iter().filter_map(|seq| hint(lexer, seq)) {
+        if !hints.contains(&hint) && hints.len() < MAX_HINTS {
+            hints.push(hint);
+        }
+    }
+
+    FangErr::SyntaxError {
+        span: spans(lexer, span),
+        message,
+        hints,
+    }
+}
+
+fn hint(lexer: &Lexer, repairs: &[Repair]) -> Option<String> {
+    let steps = repairs
+        .iter()
+        .filter_map(|r| match r {
+            ParseRepair::Insert(tidx) => Some(format!("insert `{}`", token(*tidx))),
+            ParseRepair::Delete(l) => Some(format!("delete `{}`", lexer.span_str(l.span()))),
+            ParseRepair::Shift(_) => None,
+        })
+        .collect::<Vec<String>>();
+
+    match steps.is_empty() {
+        true => None,
+        false => Some(steps.join(", then ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(src: &str) -> Vec<(String, usize, usize, Vec<String>)> {
+        parse(src)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| match e {
+                FangErr::SyntaxError {
+                    span,
+                    message,
+                    hints,
+                } => (message, span.line(), span.col(), hints),
+                e => panic!("expected a syntax error, found {e}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        let errs = errors("let a = 1\nlet b = 2;");
+
+        assert_eq!(errs.len(), 1);
+        let (message, line, col, hints) = &errs[0];
+        assert_eq!(message, "expected `;` after expression, found `let`");
+        assert_eq!((*line, *col), (1, 10));
+        assert!(hints.contains(&"insert `;`".to_string()), "{hints:?}");
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let errs = errors("let a = 1);");
+
+        assert_eq!(errs[0].0, "unexpected `)`");
+        assert_eq!((errs[0].1, errs[0].2), (1, 10));
+        assert!(errs[0].3.contains(&"delete `)`".to_string()));
+    }
+
+    #[test]
+    fn recovers_to_report_every_error() {
+        assert_eq!(errors("let a = 1\nlet b = 2\nlet c = 3;").len(), 2);
+    }
+
+    #[test]
+    fn unexpected_character() {
+        let errs = errors("let a = 1 $ 2;");
+        assert_eq!(errs[0].0, "unexpected character `$`");
+        assert_eq!((errs[0].1, errs[0].2), (1, 11));
+    }
+
+    #[test]
+    fn literal_out_of_range() {
+        let errs = errors("let a = 99999999999999999999;");
+        assert_eq!(
+            errs[0].0,
+            "99999999999999999999 cannot be represented as an integer"
+        );
+    }
+}
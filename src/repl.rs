@@ -0,0 +1,304 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    ast::Node,
+    engine::{parse_lenient, return_last, Engine},
+    errs::FangErr,
+    scope::Type,
+    value::Value,
+};
+
+const HELP: &str = "\
+:type <expr>   evaluate <expr> and print its type
+:scope         list the variables, functions and types declared so far
+:load <file>   run <file> in the current scope
+:history       list previous inputs
+:help          show this message
+:quit          exit the REPL";
+
+pub struct Repl {
+    pub engine: Engine,
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+    status: i32,
+}
+
+/// Whether `src` has more opening than closing brackets, outside of strings and comments.
+pub fn is_unbalanced(src: &str) -> bool {
+    let mut depth = 0i64;
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            _ => (),
+        }
+    }
+
+    depth > 0
+}
+
+impl Repl {
+    pub fn new(engine: Engine) -> Self {
+        let history_file = env::var_os("HOME").map(|h| PathBuf::from(h).join(".fang_history"));
+        let history = history_file
+            .as_ref()
+            .and_then(|f| fs::read_to_string(f).ok())
+            .map(|h| h.lines().map(|l| l.replace("\\n", "\n")).collect())
+            .unwrap_or_default();
+
+        Self {
+            engine,
+            history,
+            history_file,
+            status: 0,
+        }
+    }
+
+    /// Runs `src` in the REPL's scope, returning the value of its last statement if that's an expression.
+    pub fn eval(&mut self, src: &str) -> Result<Value, Vec<FangErr>> {
+        self.engine.locate();
+        let ast = parse_lenient(src)?;
+        self.engine.run(return_last(ast)).map_err(|e| vec![e])
+    }
+
+    fn describe_scope(&self) -> String {
+        let mut out = Vec::new();
+
+        let scope = self.engine.scope();
+
+        // Builtins are declared without a source location.
+        let mut names = scope
+            .store
+            .keys()
+            .filter(|n| scope.declarations.get(*n).is_some_and(|s| !s.is_empty()))
+            .collect::<Vec<&String>>();
+        names.sort();
+        for name in names {
+            let val = &scope.store[name];
+            out.push(format!(
+                "let {name}: {} = {}",
+                val.get_type(),
+                val.inspect()
+            ));
+        }
+
+        let mut names = scope.functions.keys().collect::<Vec<&String>>();
+        names.sort();
+        for name in names {
+            let (args, _, return_type) = &scope.functions[name];
+            let args = args
+                .iter()
+                .map(|a| match a {
+                    Node::TypedVariable { name, var_type, .. } => format!("{name}: {var_type}"),
+                    a => a.inspect(),
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            out.push(match return_type {
+                Some(rt) => format!("fn {name}({args}): {rt}"),
+                None => format!("fn {name}({args})"),
+            });
+        }
+
+        let mut names = scope.types.keys().collect::<Vec<&String>>();
+        names.sort();
+        for name in names {
+            out.push(match &scope.types[name] {
+                Type::Trait { .. } => format!("trait {name}"),
+                Type::Struct { implements, .. } if implements.is_empty() => {
+                    format!("struct {name}")
+                }
+                Type::Struct { implements, .. } => {
+                    format!("struct {name} implements {}", implements.join(", "))
+                }
+                Type::Native { implements, .. } if implements.is_empty() => {
+                    format!("native {name}")
+                }
+                Type::Native { implements, .. } => {
+                    format!("native {name} implements {}", implements.join(", "))
+                }
+            });
+        }
+
+        out.join("\n")
+    }
+
+    /// Handles one complete input, returning `false` once the user asks to quit.
+    pub fn handle(&mut self, input: &str) -> bool {
+        let input = input.trim();
+        if input.is_empty() {
+            return true;
+        }
+
+        self.remember(input);
+
+        let (command, arg) = input.split_once(' ').unwrap_or((input, ""));
+        let arg = arg.trim();
+        let res = match command {
+            ":quit" | ":q" | ":exit" => return false,
+            ":help" => {
+                println!("{HELP}");
+                Ok(Value::None)
+            }
+            ":history" => {
+                for (i, h) in self.history.iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, h.replace('\n', "\n      "));
+                }
+                Ok(Value::None)
+            }
+            ":scope" => {
+                println!("{}", self.describe_scope());
+                Ok(Value::None)
+            }
+            ":load" => self
+                .engine
+                .run_file(arg)
+                .map(|_| Value::None)
+                .map_err(|e| vec![e]),
+            ":type" => self.eval(arg).map(|val| {
+                println!("{}", val.get_type());
+                Value::None
+            }),
+            c if c.starts_with(':') => {
+                eprintln!("Unknown command {c}, try :help");
+                Ok(Value::None)
+            }
+            _ => self.eval(input),
+        };
+
+        match res {
+            Ok(Value::None) => (),
+            Ok(val) => println!("{val}"),
+            Err(errs) if matches!(errs[..], [FangErr::Exit { .. }]) => {
+                self.status = errs[0].exit_code();
+                return false;
+            }
+            Err(errs) => {
+                for e in errs {
+                    eprintln!("{e}\n");
+                }
+            }
+        }
+
+        true
+    }
+
+    fn remember(&mut self, input: &str) {
+        self.history.push(input.to_string());
+
+        if let Some(path) = &self.history_file {
+            if let Ok(mut f) = fs::OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(f, "{}", input.replace('\n', "\\n"));
+            }
+        }
+    }
+
+    /// Reads inputs until the user quits, returning the status given to `exit`, if any.
+    pub fn run(&mut self) -> i32 {
+        println!("Fang REPL, :help for commands");
+
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut input = String::new();
+        loop {
+            print!("{}", if input.is_empty() { "> " } else { ". " });
+            io::stdout().flush().unwrap();
+
+            let Some(Ok(line)) = lines.next() else {
+                println!();
+                break;
+            };
+
+            input.push_str(&line);
+            input.push('\n');
+            if is_unbalanced(&input) {
+                continue;
+            }
+
+            if !self.handle(&std::mem::take(&mut input)) {
+                break;
+            }
+        }
+
+        self.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repl() -> Repl {
+        Repl {
+            engine: Engine::new(),
+            history: vec![],
+            history_file: None,
+            status: 0,
+        }
+    }
+
+    fn eval(repl: &mut Repl, src: &str) -> Option<String> {
+        match repl.eval(src).unwrap() {
+            Value::None => None,
+            v => Some(v.to_string()),
+        }
+    }
+
+    #[test]
+    fn scope_persists_between_inputs() {
+        let mut r = repl();
+        assert_eq!(eval(&mut r, "let a = 2;"), None);
+        assert_eq!(
+            eval(&mut r, "fn double(x: int): int { return x * 2; }"),
+            None
+        );
+        assert_eq!(eval(&mut r, "double(a) + 1"), Some("5".to_string()));
+    }
+
+    #[test]
+    fn trailing_semicolon_is_optional() {
+        let mut r = repl();
+        assert_eq!(eval(&mut r, "1 + 2"), Some("3".to_string()));
+        assert_eq!(eval(&mut r, "1 + 2;"), Some("3".to_string()));
+        assert!(r.eval("1 +").is_err());
+    }
+
+    #[test]
+    fn exit_ends_the_session() {
+        let mut r = repl();
+        assert!(r.handle("let a = 1;"));
+        assert!(!r.handle("exit(3);"));
+        assert_eq!(r.status, 3);
+    }
+
+    #[test]
+    fn unbalanced_input() {
+        assert!(is_unbalanced("fn f(): int {\n"));
+        assert!(!is_unbalanced("fn f(): int { return 1; }"));
+        assert!(!is_unbalanced(r#"let s = "{";"#));
+        assert!(!is_unbalanced("let a = 1; // {"));
+    }
+}
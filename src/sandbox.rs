@@ -0,0 +1,241 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{BuiltinFnBody, Node, Spans},
+    errs::FangErr,
+    limits::{Limits, DEFAULT_MAX_DEPTH},
+    scope::Scope,
+};
+
+/// Something outside the engine a builtin module can reach, which untrusted scripts may be
+/// denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Printing with `console`.
+    Console,
+    /// Reading and writing files.
+    Fs,
+    /// Starting other processes and inspecting this one.
+    Process,
+    /// Reading and changing environment variables.
+    Env,
+    /// Reading the time with `os.time`.
+    Clock,
+}
+
+pub const CAPABILITIES: &[Capability] = &[
+    Capability::Console,
+    Capability::Fs,
+    Capability::Process,
+    Capability::Env,
+    Capability::Clock,
+];
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Console => "console",
+            Capability::Fs => "fs",
+            Capability::Process => "process",
+            Capability::Env => "env",
+            Capability::Clock => "clock",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        CAPABILITIES.iter().copied().find(|c| c.name() == name)
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The set of capabilities a scope's builtins may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    pub const ALL: Capabilities = Capabilities(u8::MAX);
+    pub const NONE: Capabilities = Capabilities(0);
+
+    pub fn allows(self, capability: Capability) -> bool {
+        self.0 & capability.bit() != 0
+    }
+
+    pub fn with(self, capability: Capability) -> Self {
+        Capabilities(self.0 | capability.bit())
+    }
+
+    pub fn without(self, capability: Capability) -> Self {
+        Capabilities(self.0 & !capability.bit())
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::ALL
+    }
+}
+
+/// What an engine is allowed to do: its capabilities and resource limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub capabilities: Capabilities,
+    pub max_depth: usize,
+    pub max_ops: Option<u64>,
+    pub max_heap: Option<usize>,
+}
+
+impl Profile {
+    /// Everything allowed and nothing limited, for scripts you wrote yourself.
+    pub fn trusted() -> Self {
+        Self {
+            capabilities: Capabilities::ALL,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_ops: None,
+            max_heap: None,
+        }
+    }
+
+    /// Only `console` and pure computation, bounded to ten million operations and 64MB.
+    pub fn untrusted() -> Self {
+        Self {
+            capabilities: Capabilities::NONE.with(Capability::Console),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_ops: Some(10_000_000),
+            max_heap: Some(64 * 1024 * 1024),
+        }
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.capabilities = self.capabilities.with(capability);
+        self
+    }
+
+    pub fn deny(mut self, capability: Capability) -> Self {
+        self.capabilities = self.capabilities.without(capability);
+        self
+    }
+
+    pub fn max_ops(mut self, max_ops: Option<u64>) -> Self {
+        self.max_ops = max_ops;
+        self
+    }
+
+    pub fn max_heap(mut self, max_heap: Option<usize>) -> Self {
+        self.max_heap = max_heap;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn limits(&self) -> Limits {
+        Limits::new(self.max_depth, self.max_ops, self.max_heap)
+    }
+}
+
+/// Makes the builtin functions in `node`, or in the module object it is, raise
+/// `PermissionDenied` when called from a scope without `capability`.
+pub fn gate(capability: Capability, node: Node) -> Node {
+    match node {
+        Node::BuiltinFn {
+            name,
+            args,
+            body,
+            return_type,
+            span,
+        } => {
+            let denied = name.clone();
+            let inner = body.0;
+            let body = move |scope: &Scope| match scope.capabilities.allows(capability) {
+                true => inner(scope),
+                false => Err(FangErr::PermissionDenied {
+                    span: Spans::empty(),
+                    name: denied.clone(),
+                    capability: capability.name().to_string(),
+                }),
+            };
+
+            Node::BuiltinFn {
+                name,
+                args,
+                body: BuiltinFnBody(Rc::new(body)),
+                return_type,
+                span,
+            }
+        }
+        Node::Object {
+            typed,
+            fields,
+            span,
+        } => Node::Object {
+            typed,
+            fields: Box::new(fields.into_iter().map(|f| gate(capability, f)).collect()),
+            span,
+        },
+        Node::Field { name, value, span } => Node::Field {
+            name,
+            value: Box::new(gate(capability, *value)),
+            span,
+        },
+        node => node,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    #[test]
+    fn capability_sets() {
+        let caps = Capabilities::NONE.with(Capability::Fs);
+        assert!(caps.allows(Capability::Fs));
+        assert!(!caps.allows(Capability::Env));
+        assert!(!caps.without(Capability::Fs).allows(Capability::Fs));
+        assert_eq!(Capability::from_name("clock"), Some(Capability::Clock));
+    }
+
+    #[test]
+    fn denied_capabilities_raise_permission_denied() {
+        let mut engine = Engine::new().with_profile(Profile::untrusted().deny(Capability::Console));
+        let err = engine.eval("console.log(\"hi\");").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::PermissionDenied { .. }));
+        assert_eq!(engine.eval("1 + 1").unwrap(), crate::value::Value::Int(2));
+    }
+
+    #[test]
+    fn untrusted_scripts_are_bounded() {
+        let mut engine = Engine::new().with_profile(Profile::untrusted().max_ops(Some(1000)));
+        let err = engine
+            .eval("fn spin(n: int): int { return spin(n + 1); }\nspin(0);")
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            FangErr::ExecutionBudgetExceeded { .. }
+        ));
+    }
+
+    // Values a call didn't produce once crashed the host, which a sandbox can't allow.
+    #[test]
+    fn missing_values_are_errors_not_panics() {
+        let mut engine = Engine::new()
+            .with_profile(Profile::untrusted())
+            .with_output(crate::console::Capture::new());
+        engine.register_fn("touch", || ());
+
+        for src in [
+            "let x = print(\"a\");\nx + 1;",
+            "let y: int = print(\"a\");",
+            "1 + print(\"a\");",
+            "touch() * 2;",
+            "let t = trait T { fn f(self): int; };",
+        ] {
+            assert!(engine.eval(src).is_err(), "{src}");
+        }
+    }
+}
@@ -1,17 +1,24 @@
 use std::{collections::HashMap, rc::Rc, vec};
 
-use once_cell::sync::Lazy;
-
 use crate::{
-    ast::{BuiltinFnBody, Node, Spans},
-    bytecode::{ast_to_bytecode, eval_bytecode, Op},
+    ast::{BuiltinFnBody, Node, Spans, LIST, VARIADIC},
+    bytecode::{eval_bytecode, Op},
+    console::{emit, Level, Output},
     errs::FangErr,
+    format::format,
+    fs,
+    limits::Limits,
+    math,
+    native::NativeClass,
+    os,
+    sandbox::{gate, Capabilities, Capability},
+    strings,
 };
 
 type Func = (Vec<Node>, Vec<Node>, Option<String>);
 
 macro_rules! builtin_fn {
-    ($name:literal, $body:expr, $ret:expr, $( $an: literal, $at: literal ),*) => {
+    ($name:expr, $body:expr, $ret:expr, $( $an: literal, $at: expr ),*) => {
         Node::BuiltinFn {
             span: Spans::empty(),
             name: $name.to_string(),
@@ -50,29 +57,127 @@ macro_rules! builtin_obj {
 
 macro_rules! builtin {
     ($g:ident, $n:literal, $b:expr) => {
-        $g.declare($n.to_string(), $b).unwrap()
+        $g.declare($n.to_string(), $b, &Spans::empty()).unwrap()
     };
 }
 
-pub const GLOBAL_SCOPE: Lazy<Scope> = Lazy::new(|| {
+// Writes any number of values of any type, see `console::emit`.
+fn console_fn(name: &'static str, level: Level, end: &'static str) -> Node {
+    builtin_fn!(
+        name,
+        move |args| emit(args, level, end),
+        None,
+        "values",
+        VARIADIC
+    )
+}
+
+/// A new top level scope holding the builtins.
+pub fn global_scope() -> Scope {
     let mut globe = Scope::new("<Fang>".to_string(), None);
 
     builtin!(
         globe,
         "console",
-        builtin_obj!(
-            "console",
-            "log",
-            builtin_fn!(
-                "log",
-                |args| {
-                    println!("{}", args.get("msg").unwrap().inspect());
-                    None
-                },
-                None,
-                "msg",
-                "string"
-            )
+        gate(
+            Capability::Console,
+            builtin_obj!(
+                "console",
+                "log", console_fn("log", Level::Log, "\n");
+                "info", console_fn("info", Level::Info, "\n");
+                "warn", console_fn("warn", Level::Warn, "\n");
+                "error", console_fn("error", Level::Error, "\n");
+                "debug", console_fn("debug", Level::Debug, "\n")
+            ),
+        )
+    );
+    builtin!(
+        globe,
+        "print",
+        gate(Capability::Console, console_fn("print", Level::Log, ""))
+    );
+    builtin!(
+        globe,
+        "println",
+        gate(Capability::Console, console_fn("println", Level::Log, "\n"))
+    );
+
+    builtin!(globe, "math", math::module());
+    builtin!(globe, "fs", fs::module());
+    builtin!(globe, "os", os::module());
+    builtin!(globe, "int", math::int());
+    builtin!(globe, "float", math::float());
+
+    // Varargs already arrive as a list.
+    builtin!(
+        globe,
+        "list",
+        builtin_fn!(
+            "list",
+            |args| Ok(args.get("values")),
+            Some(LIST.to_string()),
+            "values",
+            VARIADIC
+        )
+    );
+
+    builtin!(
+        globe,
+        "format",
+        builtin_fn!(
+            "format",
+            |args| {
+                let values = match args.get("values") {
+                    Some(Node::Object { fields, .. }) => fields
+                        .into_iter()
+                        .filter_map(|f| match f {
+                            Node::Field { value, .. } => Some(*value),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+                let template = args
+                    .get("template")
+                    .map(|t| t.inspect())
+                    .unwrap_or_default();
+
+                format(&template, &values)
+                    .map(|val| {
+                        Some(Node::String {
+                            val,
+                            span: Spans::empty(),
+                        })
+                    })
+                    .map_err(|message| FangErr::InvalidFormat {
+                        span: Spans::empty(),
+                        message,
+                    })
+            },
+            Some("string".to_string()),
+            "template",
+            "string",
+            "values",
+            VARIADIC
+        )
+    );
+
+    // Statuses wrap at 256, as they would in a shell.
+    builtin!(
+        globe,
+        "exit",
+        builtin_fn!(
+            "exit",
+            |args| match args.get("code") {
+                Some(Node::Integer { val, .. }) => Err(FangErr::Exit {
+                    span: Spans::empty(),
+                    code: (val % 256) as i32,
+                }),
+                _ => Ok(None),
+            },
+            None,
+            "code",
+            "int"
         )
     );
 
@@ -87,7 +192,7 @@ pub const GLOBAL_SCOPE: Lazy<Scope> = Lazy::new(|| {
     // })
 
     globe
-});
+}
 
 #[derive(Debug, Clone)]
 pub enum TraitFn {
@@ -116,13 +221,21 @@ pub enum Type {
         implements: Vec<String>,                     // traits
         implementations: Vec<HashMap<String, Func>>, // fns from traits
     },
+    /// A Rust type registered by the host, see `native::NativeType`.
+    Native {
+        name: String,
+        class: Rc<NativeClass>,
+        implements: Vec<String>,
+        implementations: Vec<HashMap<String, Func>>,
+    },
 }
 
 impl Type {
-    pub fn validate_trait(&self, name: &str, args: Vec<Node>) -> Result<(), FangErr> {
+    pub fn validate_trait(&self, name: &str, args: Vec<Node>, span: &Spans) -> Result<(), FangErr> {
         match self {
             Type::Trait { functions, .. } => {
                 let fn_args = match functions.get(name).ok_or(FangErr::UndeclaredFunction {
+                    span: span.clone(),
                     name: name.to_string(),
                     scope: "?".to_string(),
                 })? {
@@ -132,6 +245,7 @@ impl Type {
 
                 if fn_args.len() != args.len() {
                     return Err(FangErr::ArgumentLengthMismatch {
+                        span: span.clone(),
                         expected: fn_args.len(),
                         found: args.len(),
                         scope: name.to_string(),
@@ -141,6 +255,7 @@ impl Type {
                 for (arg, val) in fn_args.iter().zip(args.iter()) {
                     if arg.compare_type(val) {
                         return Err(FangErr::TypeMismatch {
+                            span: val.span(),
                             expected: arg.get_type(),
                             found: val.get_type(),
                             scope: name.to_string(),
@@ -151,6 +266,7 @@ impl Type {
                 Ok(())
             }
             _ => Err(FangErr::UnexpectedType {
+                span: span.clone(),
                 expected: "Trait".to_string(),
                 found: name.to_string(),
                 scope: name.to_string(),
@@ -158,32 +274,55 @@ impl Type {
         }
     }
 
-    pub fn validate_struct(&self, name: &str, fields: Vec<Node>) -> Result<(), FangErr> {
+    pub fn validate_struct(
+        &self,
+        name: &str,
+        fields: &[Node],
+        span: &Spans,
+    ) -> Result<(), FangErr> {
         match self {
             Type::Struct {
                 fields: expected, ..
             } => {
                 if expected.len() != fields.len() {
                     return Err(FangErr::ArgumentLengthMismatch {
+                        span: span.clone(),
                         expected: expected.len(),
                         found: fields.len(),
                         scope: name.to_string(),
                     });
                 }
 
-                for (exp, val) in expected.iter().zip(fields.iter()) {
-                    if exp.compare_type(val) {
-                        return Err(FangErr::TypeMismatch {
-                            expected: exp.get_type(),
-                            found: val.get_type(),
-                            scope: name.to_string(),
-                        });
+                for exp in expected {
+                    let Node::TypedVariable {
+                        name: field,
+                        var_type,
+                        ..
+                    } = exp
+                    else {
+                        continue;
+                    };
+                    let val = fields.iter().find_map(|f| match f {
+                        Node::Field { name, value, .. } if name == field => Some(value),
+                        _ => None,
+                    });
+                    match val {
+                        Some(val) if exp.compare_type(val) => (),
+                        val => {
+                            return Err(FangErr::TypeMismatch {
+                                span: span.clone(),
+                                expected: format!("{field}: {var_type}"),
+                                found: val.map_or("nothing".to_string(), |v| v.get_type()),
+                                scope: name.to_string(),
+                            })
+                        }
                     }
                 }
 
                 Ok(())
             }
             _ => Err(FangErr::UnexpectedType {
+                span: span.clone(),
                 expected: "Struct".to_string(),
                 found: name.to_string(),
                 scope: name.to_string(),
@@ -192,13 +331,67 @@ impl Type {
     }
 }
 
+// In a trait or impl, `self` as a type stands for the implementing type.
+fn resolve_self((args, body, return_type): &Func, ty: &str) -> Func {
+    let resolve = |t: &String| match t.as_str() {
+        "self" => ty.to_string(),
+        _ => t.clone(),
+    };
+    let args = args
+        .iter()
+        .map(|arg| match arg {
+            Node::TypedVariable {
+                name,
+                var_type,
+                span,
+            } if name != "self" => Node::TypedVariable {
+                name: name.clone(),
+                var_type: resolve(var_type),
+                span: span.clone(),
+            },
+            arg => arg.clone(),
+        })
+        .collect();
+
+    (args, body.clone(), return_type.as_ref().map(resolve))
+}
+
+// A trait method looked up on a value takes the value as `self`, so it's bound in the body
+// rather than passed.
+fn bind_self(name: &str, (args, body, return_type): &Func, receiver: Node) -> Node {
+    let args = match args.first() {
+        Some(Node::TypedVariable { name, .. }) if name == "self" => &args[1..],
+        _ => &args[..],
+    };
+    let this = Node::Declaration {
+        name: "self".to_string(),
+        rhs: Some(Box::new(receiver)),
+        var_type: None,
+        span: Spans::empty(),
+    };
+
+    Node::Function {
+        name: name.to_string(),
+        args: Box::new(args.to_vec()),
+        body: Box::new([vec![this], body.clone()].concat()),
+        return_type: return_type.clone(),
+        span: Spans::empty(),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Scope {
     pub name: String,
     pub store: HashMap<String, Node>,
     pub functions: HashMap<String, Func>,
+    pub declarations: HashMap<String, Spans>,
+    pub fn_declarations: HashMap<String, Spans>,
     pub types: HashMap<String, Type>,
     pub parent: Option<Box<Scope>>,
+    pub optimise: bool,
+    pub limits: Rc<Limits>,
+    pub capabilities: Capabilities,
+    pub output: Output,
 }
 
 impl Scope {
@@ -207,26 +400,51 @@ impl Scope {
             name,
             store: HashMap::new(),
             functions: HashMap::new(),
+            declarations: HashMap::new(),
+            fn_declarations: HashMap::new(),
             types: HashMap::new(),
+            optimise: parent.as_ref().is_some_and(|p| p.optimise),
+            limits: parent
+                .as_ref()
+                .map(|p| p.limits.clone())
+                .unwrap_or_default(),
+            capabilities: parent.as_ref().map(|p| p.capabilities).unwrap_or_default(),
+            output: parent
+                .as_ref()
+                .map(|p| p.output.clone())
+                .unwrap_or_default(),
             parent,
         }
     }
 
-    pub fn declare(&mut self, name: String, val: Node) -> Result<(), FangErr> {
+    pub fn declare(&mut self, name: String, val: Node, span: &Spans) -> Result<(), FangErr> {
         if self.store.contains_key(&name) {
             return Err(FangErr::AlreadyDeclaredVariable {
+                span: span.clone(),
+                previous: self.declarations.get(&name).cloned(),
                 name,
                 scope: self.name.clone(),
             });
         }
 
+        self.declarations.insert(name.clone(), span.clone());
         self.store.insert(name, val);
         Ok(())
     }
 
-    pub fn assign(&mut self, name: String, val: Node) -> Result<(), FangErr> {
+    pub fn assign(&mut self, name: String, val: Node, span: &Spans) -> Result<(), FangErr> {
+        // Fields of Rust values are written through their setters.
+        if let Some((path, field)) = name.rsplit_once('.') {
+            if let Some(Node::Native { typed, handle, .. }) = self.get(path) {
+                if let Some(Type::Native { class, .. }) = self.get_type(&typed) {
+                    return class.set(&handle, field, val, span, &self.name);
+                }
+            }
+        }
+
         if !self.store.contains_key(&name) {
             return Err(FangErr::UndeclaredVariable {
+                span: span.clone(),
                 name,
                 scope: self.name.clone(),
             });
@@ -234,6 +452,7 @@ impl Scope {
 
         if !self.store.get(&name).unwrap().compare_type(&val) {
             return Err(FangErr::TypeMismatch {
+                span: span.clone(),
                 expected: self.store.get(&name).unwrap().get_type(),
                 found: val.get_type(),
                 scope: self.name.clone(),
@@ -250,77 +469,123 @@ impl Scope {
         args: Vec<Node>,
         body: Vec<Node>,
         return_type: Option<String>,
+        span: &Spans,
     ) -> Result<(), FangErr> {
         if self.functions.contains_key(&name) {
             return Err(FangErr::AlreadyDeclaredFunction {
+                span: span.clone(),
+                previous: self.fn_declarations.get(&name).cloned(),
                 name,
                 scope: self.name.clone(),
             });
         }
 
-        if return_type.is_some() {}
-
+        self.fn_declarations.insert(name.clone(), span.clone());
         self.functions.insert(name, (args, body, return_type));
         Ok(())
     }
 
     pub fn get(&self, name: &str) -> Option<Node> {
         if name.contains('.') {
-            let mut parts = name.split('.').collect::<Vec<&str>>();
-            parts.reverse();
+            let mut parts = name.split('.');
 
-            let mut container = self.store.get(parts.pop().unwrap()).cloned().or(self
-                .parent
-                .as_ref()
-                .map(|p| p.get(name))
-                .flatten());
+            let mut container = self
+                .store
+                .get(parts.next().unwrap())
+                .cloned()
+                .or(self.parent.as_ref().and_then(|p| p.get(name)));
 
             for part in parts {
+                // Each step finds a field, and the next looks in its value.
+                if let Some(Node::Field { value, .. }) = container {
+                    container = Some(*value);
+                }
                 container = match container {
-                    Some(Node::Object { fields, typed, .. }) => fields
-                        .iter()
-                        .find(|p| match p {
-                            Node::Field { name, .. } => name == part,
-                            _ => false,
-                        })
-                        .cloned()
-                        .or(self.get_implementations_for(&typed).iter().find_map(|i| {
-                            i.get(part).map(|f| Node::Function {
+                    Some(object @ Node::Object { .. }) => {
+                        let Node::Object { fields, typed, .. } = &object else {
+                            unreachable!()
+                        };
+                        fields
+                            .iter()
+                            .find(|p| match p {
+                                Node::Field { name, .. } => name == part,
+                                _ => false,
+                            })
+                            .cloned()
+                            .or_else(|| {
+                                self.get_implementations_for(typed)
+                                    .iter()
+                                    .find_map(|i| i.get(part))
+                                    .map(|f| Node::Field {
+                                        name: part.to_string(),
+                                        value: Box::new(bind_self(part, f, object.clone())),
+                                        span: Spans::empty(),
+                                    })
+                            })
+                    }
+                    Some(Node::Native { typed, handle, .. }) => match self.get_type(&typed) {
+                        Some(Type::Native { class, .. }) => class
+                            .get(&handle, part)
+                            .or_else(|| {
+                                self.get_implementations_for(&typed)
+                                    .iter()
+                                    .find_map(|i| i.get(part))
+                                    .map(|f| {
+                                        let handle = Node::Native {
+                                            typed: typed.clone(),
+                                            handle: handle.clone(),
+                                            span: Spans::empty(),
+                                        };
+                                        bind_self(part, f, handle)
+                                    })
+                            })
+                            .map(|value| Node::Field {
                                 name: part.to_string(),
-                                args: Box::new(f.0.clone()),
-                                body: Box::new(f.1.clone()),
-                                return_type: f.2.clone(),
+                                value: Box::new(value),
                                 span: Spans::empty(),
-                            })
-                        })),
+                            }),
+                        _ => None,
+                    },
+                    Some(Node::String { val, .. }) => {
+                        strings::method(&val, part).map(|f| Node::Field {
+                            name: part.to_string(),
+                            value: Box::new(f),
+                            span: Spans::empty(),
+                        })
+                    }
                     _ => None,
                 };
             }
 
             match container {
                 Some(Node::Field { value, .. }) => Some(*value),
-                _ => return None,
+                _ => None,
             }
         } else {
             self.store
                 .get(name)
                 .cloned()
-                .or(self.parent.as_ref().map(|p| p.get(name)).flatten())
+                .or(self.parent.as_ref().and_then(|p| p.get(name)))
         }
     }
 
+    pub fn heap_size(&self) -> usize {
+        self.store.values().map(Node::heap_size).sum::<usize>()
+            + self.parent.as_ref().map_or(0, |p| p.heap_size())
+    }
+
     pub fn get_type(&self, name: &str) -> Option<&Type> {
         self.types
             .get(name)
-            .or(self.parent.as_ref().map(|p| p.get_type(name)).flatten())
+            .or(self.parent.as_ref().and_then(|p| p.get_type(name)))
     }
 
     pub fn get_args(&self, name: &str) -> Option<Vec<Node>> {
         self.get_fn(name)
             .map(|(args, _, _)| args.clone())
             .or(self.get(name).map(|n| match n {
-                Node::Function { args, .. } => args.iter().cloned().collect(),
-                Node::BuiltinFn { args, .. } => args.iter().cloned().collect(),
+                Node::Function { args, .. } => args.to_vec(),
+                Node::BuiltinFn { args, .. } => args.to_vec(),
                 _ => vec![],
             }))
     }
@@ -329,46 +594,63 @@ impl Scope {
         self.functions
             .get(name)
             .cloned()
-            .or(self
-                .get(name)
-                .map(|n| match n {
-                    Node::Function {
-                        args,
-                        body,
-                        return_type,
-                        ..
-                    } => Some((*args, *body, return_type)),
-                    Node::BuiltinFn {
-                        name,
-                        args,
-                        body,
-                        return_type,
-                        ..
-                    } => Some((
-                        *args.clone(),
-                        vec![Node::BuiltinFn {
-                            name: name.clone(),
-                            args: args.clone(),
-                            body: body.clone(),
-                            return_type: return_type.clone(),
-                            span: Spans::empty(),
-                        }],
-                        return_type,
-                    )),
-                    _ => None,
-                })
-                .flatten())
-            .or(self.parent.as_ref().map(|p| p.get_fn(name)).flatten())
+            .or(self.get(name).and_then(|n| match n {
+                Node::Function {
+                    args,
+                    body,
+                    return_type,
+                    ..
+                } => Some((*args, *body, return_type)),
+                Node::BuiltinFn {
+                    name,
+                    args,
+                    body,
+                    return_type,
+                    ..
+                } => Some((
+                    *args.clone(),
+                    vec![Node::BuiltinFn {
+                        name: name.clone(),
+                        args: args.clone(),
+                        body: body.clone(),
+                        return_type: return_type.clone(),
+                        span: Spans::empty(),
+                    }],
+                    return_type,
+                )),
+                _ => None,
+            }))
+            .or(self.parent.as_ref().and_then(|p| p.get_fn(name)))
+    }
+
+    pub fn call(&self, name: &str, args: Vec<Node>, span: &Spans) -> Result<Vec<Op>, FangErr> {
+        let (mut scope, body) = self.frame(name, Some(Box::new(self.clone())), args, span)?;
+
+        // A call always leaves a value, `none` if the function returned nothing.
+        let mut ops = Vec::<Op>::new();
+        match eval_bytecode(body.to_vec(), &mut scope)? {
+            Some(n) if !matches!(n, Node::Empty) => ops.push(Op::Push { value: n }),
+            _ => ops.push(Op::Push { value: Node::Empty }),
+        }
+        Ok(ops)
     }
 
-    pub fn call(&self, name: &str, args: Vec<Node>) -> Result<Vec<Op>, FangErr> {
+    /// Builds the scope a call to `name` runs in, with its parameters bound to `args`.
+    pub fn frame(
+        &self,
+        name: &str,
+        parent: Option<Box<Scope>>,
+        args: Vec<Node>,
+        span: &Spans,
+    ) -> Result<(Scope, Vec<Node>), FangErr> {
         let func = self.get_fn(name);
-        let (fn_args, body, _) = func.ok_or(FangErr::UndeclaredVariable {
+        let (fn_args, body, _) = func.ok_or_else(|| FangErr::UndeclaredFunction {
+            span: span.clone(),
             name: name.to_string(),
             scope: self.name.clone(),
         })?;
 
-        let mut scope = Scope::new(name.to_string(), Some(Box::new(self.clone())));
+        let mut scope = Scope::new(name.to_string(), parent);
         for (arg, val) in fn_args.iter().zip(args.iter()) {
             scope.declare(
                 match arg {
@@ -376,6 +658,7 @@ impl Scope {
                     Node::TypedVariable { name, .. } => name.clone(),
                     _ => {
                         return Err(FangErr::UnexpectedToken {
+                            span: arg.span(),
                             expected: "Identifier".to_string(),
                             found: val.get_type(),
                             scope: name.to_string(),
@@ -383,18 +666,23 @@ impl Scope {
                     }
                 },
                 val.clone(),
+                &arg.span(),
             )?;
         }
 
-        let mut ops = Vec::<Op>::new();
-        eval_bytecode(body.to_vec(), &mut scope)?.map(|n| ast_to_bytecode(n, &mut ops));
-        Ok(ops)
+        Ok((scope, body))
     }
 
-    pub fn define_struct(&mut self, name: String, fields: Vec<Node>) -> Result<(), FangErr> {
+    pub fn define_struct(
+        &mut self,
+        name: String,
+        fields: Vec<Node>,
+        span: &Spans,
+    ) -> Result<(), FangErr> {
         if self.types.contains_key(&name) {
             return Err(FangErr::AlreadyDeclaredStruct {
-                name: name,
+                span: span.clone(),
+                name,
                 scope: self.name.clone(),
             });
         }
@@ -415,9 +703,11 @@ impl Scope {
         &mut self,
         name: String,
         functions: HashMap<String, TraitFn>,
+        span: &Spans,
     ) -> Result<(), FangErr> {
         if self.types.contains_key(&name) {
             return Err(FangErr::AlreadyDeclaredTrait {
+                span: span.clone(),
                 name,
                 scope: self.name.clone(),
             });
@@ -431,7 +721,7 @@ impl Scope {
     pub fn get_mut_type(&mut self, name: String) -> Option<&mut Type> {
         self.types
             .get_mut(&name)
-            .or(self.parent.as_mut().map(|p| p.get_mut_type(name)).flatten())
+            .or(self.parent.as_mut().and_then(|p| p.get_mut_type(name)))
     }
 
     pub fn implement(
@@ -439,12 +729,17 @@ impl Scope {
         struct_name: String,
         trait_name: String,
         implementation: HashMap<String, Func>,
+        span: &Spans,
     ) -> Result<(), FangErr> {
         let scope_name = self.name.clone();
+        let implementation: HashMap<String, Func> = implementation
+            .iter()
+            .map(|(name, f)| (name.clone(), resolve_self(f, &struct_name)))
+            .collect();
 
         self.get_type(&trait_name)
             .map(|t| {
-                Ok(match t {
+                match t {
                     Type::Trait { functions, .. } => {
                         for (name, args, ret) in functions
                             .iter()
@@ -455,7 +750,13 @@ impl Scope {
                                     TraitFn::NoBody {
                                         args, return_type, ..
                                     },
-                                ) => (name.clone(), args.clone(), return_type.clone()),
+                                ) => {
+                                    let (args, _, return_type) = resolve_self(
+                                        &(args.clone(), vec![], return_type.clone()),
+                                        &struct_name,
+                                    );
+                                    (name.clone(), args, return_type)
+                                }
                                 _ => unreachable!(),
                             })
                             .collect::<Vec<(String, Vec<Node>, Option<String>)>>()
@@ -464,6 +765,7 @@ impl Scope {
                                 implementation
                                     .get(&name)
                                     .ok_or(FangErr::UndeclaredFunction {
+                                        span: span.clone(),
                                         name: name.clone(),
                                         scope: scope_name.clone(),
                                     })?;
@@ -472,6 +774,7 @@ impl Scope {
                                 match (
                                     arg,
                                     imple.0.get(i).ok_or(FangErr::UndeclaredVariable {
+                                        span: span.clone(),
                                         name: name.clone(),
                                         scope: scope_name.clone(),
                                     })?,
@@ -486,6 +789,7 @@ impl Scope {
                                     ) => {
                                         if var_type != imple_type {
                                             return Err(FangErr::TypeMismatch {
+                                                span: imple.0[i].span(),
                                                 expected: var_type.clone(),
                                                 found: imple_type.clone(),
                                                 scope: scope_name.clone(),
@@ -494,6 +798,7 @@ impl Scope {
                                     }
                                     _ => {
                                         return Err(FangErr::UnexpectedToken {
+                                            span: imple.0[i].span(),
                                             expected: "TypedVariable".to_string(),
                                             found: arg.get_type(),
                                             scope: scope_name.clone(),
@@ -506,6 +811,7 @@ impl Scope {
                                 (Some(rt), Some(irt)) => {
                                     if rt != irt {
                                         return Err(FangErr::TypeMismatch {
+                                            span: span.clone(),
                                             expected: rt.clone(),
                                             found: irt.clone(),
                                             scope: scope_name.clone(),
@@ -515,6 +821,7 @@ impl Scope {
                                 (None, None) => (),
                                 _ => {
                                     return Err(FangErr::UnexpectedToken {
+                                        span: span.clone(),
                                         expected: "None".to_string(),
                                         found: "Some".to_string(),
                                         scope: scope_name.clone(),
@@ -525,14 +832,17 @@ impl Scope {
                     }
                     _ => {
                         return Err(FangErr::UnexpectedType {
+                            span: span.clone(),
                             expected: "Trait".to_string(),
                             found: trait_name.clone(),
                             scope: scope_name.clone(),
                         })
                     }
-                })
+                }
+                Ok(())
             })
             .unwrap_or(Err(FangErr::UndeclaredType {
+                span: span.clone(),
                 name: trait_name.clone(),
                 scope: scope_name.clone(),
             }))?;
@@ -540,23 +850,36 @@ impl Scope {
         let ty = self
             .get_mut_type(struct_name.clone())
             .ok_or(FangErr::UndeclaredType {
+                span: span.clone(),
                 name: struct_name.clone(),
                 scope: scope_name.clone(),
             })?;
 
         match ty {
-            Type::Struct { implements, .. } => {
+            Type::Struct {
+                implements,
+                implementations,
+                ..
+            }
+            | Type::Native {
+                implements,
+                implementations,
+                ..
+            } => {
                 if implements.contains(&trait_name) {
                     return Err(FangErr::AlreadyImplementedTrait {
+                        span: span.clone(),
                         name: trait_name,
                         scope: self.name.clone(),
                     });
                 }
 
                 implements.push(trait_name);
+                implementations.push(implementation);
             }
             _ => {
                 return Err(FangErr::UnexpectedType {
+                    span: span.clone(),
                     expected: "Struct".to_string(),
                     found: struct_name,
                     scope: scope_name,
@@ -567,34 +890,45 @@ impl Scope {
         Ok(())
     }
 
+    /// The methods `name` has from the traits it implements, its own implementations before
+    /// the traits' defaults.
     pub fn get_implementations_for(&self, name: &str) -> Vec<HashMap<String, Func>> {
         self.get_type(name)
             .map(|ty| match ty {
-                Type::Struct { implements, .. } => implements
-                    .clone()
+                Type::Struct {
+                    implements,
+                    implementations,
+                    ..
+                }
+                | Type::Native {
+                    implements,
+                    implementations,
+                    ..
+                } => implementations
                     .iter()
-                    .map(|i| {
+                    .cloned()
+                    .chain(implements.iter().map(|i| {
                         self.get_type(i)
                             .map(|t| match t {
                                 Type::Trait { functions, .. } => functions
-                                    .into_iter()
-                                    .filter(|f| match f {
-                                        (_, TraitFn::Default { .. }) => true,
-                                        _ => false,
-                                    })
-                                    .map(|(name, f)| {
+                                    .iter()
+                                    .filter(|f| matches!(f, (_, TraitFn::Default { .. })))
+                                    .map(|(fn_name, f)| {
                                         (
-                                            name,
+                                            fn_name,
                                             match f {
                                                 TraitFn::Default {
                                                     args,
                                                     body,
                                                     return_type,
                                                     ..
-                                                } => (
-                                                    args.clone(),
-                                                    body.clone(),
-                                                    return_type.clone(),
+                                                } => resolve_self(
+                                                    &(
+                                                        args.clone(),
+                                                        body.clone(),
+                                                        return_type.clone(),
+                                                    ),
+                                                    name,
                                                 ),
                                                 _ => unreachable!(),
                                             },
@@ -605,10 +939,10 @@ impl Scope {
                                 _ => unreachable!(),
                             })
                             .unwrap()
-                    })
+                    }))
                     .collect(),
                 _ => unreachable!(),
             })
-            .unwrap_or(Vec::new())
+            .unwrap_or_default()
     }
 }
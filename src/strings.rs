@@ -0,0 +1,154 @@
+use crate::{
+    ast::{Node, Spans},
+    errs::FangErr,
+    native::native,
+};
+
+/// Replaces the escape sequences in the body of a string literal with what they stand for.
+pub fn unescape(src: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        out.push(match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('\\') => '\\',
+            Some('u') => {
+                let code: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                code.strip_prefix('{')
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or(format!("invalid unicode escape `\\u{code}}}`"))?
+            }
+            Some(c) => return Err(format!("unknown escape sequence `\\{c}`")),
+            None => return Err("string ends with a lone `\\`".to_string()),
+        });
+    }
+
+    Ok(out)
+}
+
+/// The length of the escape sequence at the start of `src`, which begins with a backslash.
+pub fn escape_len(src: &str) -> usize {
+    match src.strip_prefix("\\u{") {
+        Some(rest) => rest.find('}').map_or(src.len(), |end| 3 + end + 1),
+        None => 1 + src[1..].chars().next().map_or(0, char::len_utf8),
+    }
+}
+
+fn invalid(message: String) -> FangErr {
+    FangErr::InvalidValue {
+        span: Spans::empty(),
+        message,
+    }
+}
+
+/// The method `name` of the string `s`, bound to it.
+pub fn method(s: &str, name: &str) -> Option<Node> {
+    let s = s.to_string();
+    Some(match name {
+        "len" => native(name, move || s.chars().count() as u64),
+        "chars" => native(name, move || {
+            s.chars().map(String::from).collect::<Vec<String>>()
+        }),
+        "split" => native(name, move |sep: String| {
+            s.split(&sep).map(String::from).collect::<Vec<String>>()
+        }),
+        "trim" => native(name, move || s.trim().to_string()),
+        "replace" => native(name, move |from: String, to: String| s.replace(&from, &to)),
+        "contains" => native(name, move |part: String| s.contains(&part)),
+        "starts_with" => native(name, move |part: String| s.starts_with(&part)),
+        "ends_with" => native(name, move |part: String| s.ends_with(&part)),
+        "to_upper" => native(name, move || s.to_uppercase()),
+        "to_lower" => native(name, move || s.to_lowercase()),
+        "parse_int" => native(name, move || {
+            s.trim()
+                .parse::<u64>()
+                .map_err(|_| invalid(format!("`{s}` isn't an int")))
+        }),
+        "parse_float" => native(name, move || {
+            s.trim()
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("`{s}` isn't a float")))
+        }),
+        // Indices count characters rather than bytes, and are clamped to the string.
+        "slice" => native(name, move |start: u64, end: u64| {
+            s.chars()
+                .skip(start as usize)
+                .take(end.saturating_sub(start) as usize)
+                .collect::<String>()
+        }),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::Engine, value::Value};
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            unescape(r#"a\n\t\"b\\\u{1F600}"#).unwrap(),
+            "a\n\t\"b\\\u{1F600}"
+        );
+        assert!(unescape(r"\q").is_err());
+        assert!(unescape(r"\u{zz}").is_err());
+        assert_eq!(escape_len(r"\u{41}rest"), 6);
+        assert_eq!(escape_len(r"\nrest"), 2);
+    }
+
+    #[test]
+    fn literals() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval(r#""a\tb""#).unwrap(), string("a\tb"));
+        assert_eq!(engine.eval(r#"r"C:\dir\n""#).unwrap(), string(r"C:\dir\n"));
+        assert_eq!(
+            engine.eval("\"\"\"\nsay \"hi\"\n  twice\"\"\"").unwrap(),
+            string("say \"hi\"\n  twice")
+        );
+        assert_eq!(engine.eval(r#"f"{1}\n\u{7b}""#).unwrap(), string("1\n{"));
+    }
+
+    #[test]
+    fn methods() {
+        let mut engine = Engine::new();
+        engine.set_global("s", string("  Héllo, World  "));
+        assert_eq!(engine.eval("s.len()").unwrap(), Value::Int(16));
+        engine.eval("let t = s.trim();").unwrap();
+        assert_eq!(engine.eval("t.to_upper()").unwrap(), string("HÉLLO, WORLD"));
+        assert_eq!(engine.eval("t.slice(1, 5)").unwrap(), string("éllo"));
+        assert_eq!(engine.eval("t.slice(10, 99)").unwrap(), string("ld"));
+        assert_eq!(
+            engine.eval("t.replace(\"l\", \"L\")").unwrap(),
+            string("HéLLo, WorLd")
+        );
+        assert_eq!(
+            engine.eval("t.starts_with(\"Hé\")").unwrap(),
+            Value::Bool(true)
+        );
+
+        engine.eval("let parts = t.split(\", \");").unwrap();
+        assert_eq!(engine.eval("parts.1").unwrap(), string("World"));
+        engine.eval("let n = \" 42 \";").unwrap();
+        assert_eq!(engine.eval("n.parse_int()").unwrap(), Value::Int(42));
+
+        let err = engine.eval("t.parse_float()").unwrap_err();
+        assert!(matches!(err.kind(), FangErr::InvalidValue { .. }));
+    }
+}
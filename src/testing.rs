@@ -0,0 +1,5 @@
+use crate::{ast::Node, parser};
+
+pub fn parse(src: &str) -> Vec<Node> {
+    parser::parse(src).unwrap_or_else(|errs| panic!("failed to parse {src:?}: {errs:?}"))
+}
@@ -0,0 +1,115 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::ast::{NativeHandle, Node, Spans};
+
+/// A Fang value as seen from Rust.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    None,
+    Int(u64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    /// An object with the name of its type and its fields, in order.
+    Object {
+        typed: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// A function, kept as its definition so it can be handed back to Fang.
+    Function(Node),
+    /// A Rust value of a type registered with `Engine::register_type`.
+    Native {
+        typed: String,
+        handle: NativeHandle,
+    },
+}
+
+impl Value {
+    pub fn from_node(node: Node) -> Self {
+        match node {
+            Node::Integer { val, .. } => Value::Int(val),
+            Node::Float { val, .. } => Value::Float(val),
+            Node::String { val, .. } => Value::String(val),
+            Node::Boolean { val, .. } => Value::Bool(val),
+            Node::Object { typed, fields, .. } => Value::Object {
+                typed,
+                fields: fields
+                    .into_iter()
+                    .filter_map(|f| match f {
+                        Node::Field { name, value, .. } => Some((name, Value::from_node(*value))),
+                        _ => None,
+                    })
+                    .collect(),
+            },
+            f @ (Node::Function { .. } | Node::BuiltinFn { .. }) => Value::Function(f),
+            Node::Native { typed, handle, .. } => Value::Native { typed, handle },
+            _ => Value::None,
+        }
+    }
+
+    /// Converts the value into a node without a source location.
+    pub fn into_node(self) -> Node {
+        let span = Spans::empty();
+        match self {
+            Value::None => Node::Empty,
+            Value::Int(val) => Node::Integer { val, span },
+            Value::Float(val) => Node::Float { val, span },
+            Value::String(val) => Node::String { val, span },
+            Value::Bool(val) => Node::Boolean { val, span },
+            Value::Object { typed, fields } => Node::Object {
+                typed,
+                fields: Box::new(
+                    fields
+                        .into_iter()
+                        .map(|(name, value)| Node::Field {
+                            name,
+                            value: Box::new(value.into_node()),
+                            span: Spans::empty(),
+                        })
+                        .collect(),
+                ),
+                span,
+            },
+            Value::Function(f) => f,
+            Value::Native { typed, handle } => Node::Native {
+                typed,
+                handle,
+                span,
+            },
+        }
+    }
+
+    pub fn get_type(&self) -> String {
+        match self {
+            Value::None => "none".to_string(),
+            v => v.clone().into_node().get_type(),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Value::None => write!(f, "none"),
+            v => write!(f, "{}", v.clone().into_node().inspect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_nodes() {
+        let v = Value::Object {
+            typed: "Point".to_string(),
+            fields: vec![
+                ("x".to_string(), Value::Int(1)),
+                ("label".to_string(), Value::String("a".to_string())),
+            ],
+        };
+        assert_eq!(Value::from_node(v.clone().into_node()), v);
+        assert_eq!(Value::from_node(Node::Empty), Value::None);
+    }
+}